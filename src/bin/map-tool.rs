@@ -0,0 +1,473 @@
+//! A tool for converting between ASCII maps and binary maps.
+//!
+//! Usage:
+//!     map-tool compile <input.txt> <output> [--table <table.txt>]
+//!     map-tool decompile <input> <output.txt> [--table <table.txt>]
+//!
+//! An output of `-` writes to stdout. See `docs/README.md` for a description of the ASCII format
+//! and the glyph table.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use macroquad::prelude::Vec2;
+use platformer::{
+    map::{LayerKind, Map, ObjectSpawn, ParallaxLayer, TileLayer},
+    tiles::TileSet,
+};
+
+/// The glyphs used by the original map, where the index of each glyph is its tile id
+static DEFAULT_TABLE: &str = r#".QWERTYUIOP{ASDFGHJKL:"ZXCVBNM<>?1"#;
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {e:#}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let mut positional = vec![];
+    let mut table_path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--table" => {
+                table_path =
+                    Some(args.next().ok_or_else(|| anyhow::anyhow!("--table needs a path"))?)
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let table = match &table_path {
+        Some(path) => GlyphTable::load(path.as_ref())?,
+        None => GlyphTable::default_table(),
+    };
+
+    match positional.as_slice() {
+        [command, input, output] if command == "compile" => {
+            let text = std::fs::read_to_string(input)
+                .with_context(|| format!("failed to open: {input}"))?;
+            let map =
+                compile(&text, &table).with_context(|| format!("failed to compile {input}"))?;
+            map.save_map(output.as_ref())
+        }
+        [command, input, output] if command == "decompile" => {
+            let map = Map::read_map(input.as_ref())?;
+            let text =
+                decompile(&map, &table).with_context(|| format!("failed to decompile {input}"))?;
+            write_output(output, &text)
+        }
+        _ => anyhow::bail!(
+            "Usage:\n    map-tool compile <input.txt> <output> [--table <table.txt>]\n    \
+             map-tool decompile <input> <output.txt> [--table <table.txt>]"
+        ),
+    }
+}
+
+fn write_output(output: &str, text: &str) -> anyhow::Result<()> {
+    if output == "-" {
+        print!("{text}");
+        Ok(())
+    }
+    else {
+        let path = PathBuf::from(output);
+        std::fs::write(&path, text).with_context(|| format!("failed to write: {}", path.display()))
+    }
+}
+
+/// A mapping between the glyphs in an ASCII map and tile ids
+struct GlyphTable {
+    tiles: HashMap<char, u16>,
+    glyphs: HashMap<u16, char>,
+}
+
+impl GlyphTable {
+    fn new(entries: impl IntoIterator<Item = (char, u16)>) -> anyhow::Result<Self> {
+        let mut table = Self { tiles: HashMap::new(), glyphs: HashMap::new() };
+        for (glyph, tile) in entries {
+            if glyph == '@' || glyph.is_whitespace() {
+                anyhow::bail!("'{glyph}' can not be used as a glyph");
+            }
+            if table.tiles.insert(glyph, tile).is_some() {
+                anyhow::bail!("Glyph '{glyph}' is used more than once");
+            }
+            if let Some(other) = table.glyphs.insert(tile, glyph) {
+                anyhow::bail!("Tile {tile} is used by both '{other}' and '{glyph}'");
+            }
+        }
+        Ok(table)
+    }
+
+    fn default_table() -> Self {
+        Self::new(DEFAULT_TABLE.chars().zip(0..)).unwrap()
+    }
+
+    /// Loads a table from a file. Each line has a glyph followed by the tile id it represents, and
+    /// lines starting with `//` are comments.
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to open: {}", path.display()))?;
+        let entries = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with("//"))
+            .map(|(i, line)| {
+                let error = || anyhow::anyhow!("line {}: expected '<glyph> <tile id>'", i + 1);
+                let mut chars = line.chars();
+                let glyph = chars.next().ok_or_else(error)?;
+                let tile = chars.as_str().trim().parse().map_err(|_| error())?;
+                Ok((glyph, tile))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .with_context(|| format!("Invalid glyph table: {}", path.display()))?;
+        Self::new(entries).with_context(|| format!("Invalid glyph table: {}", path.display()))
+    }
+}
+
+/// Compiles an ASCII map
+fn compile(text: &str, table: &GlyphTable) -> anyhow::Result<Map> {
+    let mut name = String::new();
+    let mut spawn = None;
+    let mut tileset_path = None;
+    let mut objects = vec![];
+    let mut parallax = vec![];
+    // Rows before the first `@layer` directive belong to the collision layer
+    let mut layers: Vec<(LayerKind, Vec<Vec<u16>>)> = vec![(LayerKind::Collision, vec![])];
+    let mut width = None;
+
+    // Maps saved on Windows end their lines with "\r\n"
+    let lines: Vec<&str> =
+        text.lines().map(|line| line.strip_suffix('\r').unwrap_or(line)).collect();
+    for (i, &line) in lines.iter().enumerate() {
+        let line_num = i + 1;
+        if let Some(directive) = line.strip_prefix('@') {
+            let (command, args) = directive.split_once(' ').unwrap_or((directive, ""));
+            let args: Vec<&str> = args.split_whitespace().collect();
+            let error = |message: &str| anyhow::anyhow!("line {line_num}: {message}");
+            match (command, args.as_slice()) {
+                ("name", _) => name = directive["name".len()..].trim().to_owned(),
+                ("spawn", [x, y]) => spawn = Some(parse_position(x, y).map_err(error)?),
+                ("tileset", [path]) => tileset_path = Some(path.to_string()),
+                ("object", [kind, x, y, properties @ ..]) => {
+                    let position = parse_position(x, y).map_err(error)?;
+                    let properties = properties
+                        .iter()
+                        .map(|property| {
+                            let (key, value) = property
+                                .split_once('=')
+                                .ok_or_else(|| error("expected properties as key=value"))?;
+                            Ok((key.to_owned(), value.to_owned()))
+                        })
+                        .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+                    objects.push(ObjectSpawn { kind: kind.to_string(), position, properties });
+                }
+                ("parallax", [texture, options @ ..]) => {
+                    let mut layer = ParallaxLayer {
+                        texture: texture.to_string(),
+                        scroll: Vec2::ONE,
+                        offset: Vec2::ZERO,
+                        repeat_x: false,
+                        repeat_y: false,
+                    };
+                    for option in options {
+                        match option.split_once('=') {
+                            Some(("scroll", value)) => {
+                                layer.scroll = parse_pair(value).map_err(error)?
+                            }
+                            Some(("offset", value)) => {
+                                layer.offset = parse_pair(value).map_err(error)?
+                            }
+                            Some(("repeat", value @ ("x" | "y" | "xy" | "none"))) => {
+                                layer.repeat_x = value.contains('x');
+                                layer.repeat_y = value.contains('y');
+                            }
+                            _ => return Err(error(&format!("invalid parallax option '{option}'"))),
+                        }
+                    }
+                    parallax.push(layer);
+                }
+                ("layer", [kind]) => {
+                    let kind = match *kind {
+                        "background" => LayerKind::Background,
+                        "collision" => LayerKind::Collision,
+                        "foreground" => LayerKind::Foreground,
+                        _ => return Err(error(&format!("unknown layer kind '{kind}'"))),
+                    };
+                    // Replace the implicit collision layer if it has no rows
+                    if layers.len() == 1 && layers[0].1.is_empty() {
+                        layers.clear();
+                    }
+                    layers.push((kind, vec![]));
+                }
+                _ => return Err(error(&format!("invalid directive '@{directive}'"))),
+            }
+            continue;
+        }
+
+        if line.is_empty() {
+            // Blank lines are only allowed at the end of the file
+            if lines[i..].iter().all(|line| line.is_empty()) {
+                break;
+            }
+            anyhow::bail!("line {line_num}: empty row");
+        }
+
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(column, glyph)| {
+                table.tiles.get(&glyph).copied().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "line {line_num}, column {}: unknown glyph '{glyph}'",
+                        column + 1
+                    )
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        match width {
+            None => width = Some(row.len()),
+            Some(width) if width != row.len() => anyhow::bail!(
+                "line {line_num}: row has {} columns, but the map is {width} columns wide",
+                row.len()
+            ),
+            _ => {}
+        }
+        layers.last_mut().unwrap().1.push(row);
+    }
+
+    let width = width.ok_or_else(|| anyhow::anyhow!("Map has no rows"))?;
+    let height = layers[0].1.len();
+    for (kind, rows) in &layers {
+        if rows.len() != height {
+            anyhow::bail!(
+                "{kind:?} layer has {} rows, but the map is {height} rows high",
+                rows.len()
+            );
+        }
+    }
+
+    let layers =
+        layers.into_iter().map(|(kind, rows)| TileLayer { kind, tiles: rows.concat() }).collect();
+    let mut map = Map::with_layers(width, height, layers, TileSet::empty())?;
+    map.name = name;
+    map.spawn = spawn;
+    map.tileset_path = tileset_path;
+    map.objects = objects;
+    map.parallax = parallax;
+    Ok(map)
+}
+
+fn parse_position(x: &str, y: &str) -> Result<Vec2, &'static str> {
+    match (x.parse(), y.parse()) {
+        (Ok(x), Ok(y)) => Ok(Vec2::new(x, y)),
+        _ => Err("invalid position"),
+    }
+}
+
+/// Parses a pair of numbers written as `x,y`
+fn parse_pair(value: &str) -> Result<Vec2, &'static str> {
+    let (x, y) = value.split_once(',').ok_or("expected a pair of numbers as x,y")?;
+    parse_position(x, y)
+}
+
+/// Converts a map back to ASCII
+fn decompile(map: &Map, table: &GlyphTable) -> anyhow::Result<String> {
+    // ASCII maps are made of rows of glyphs, so they can't describe a map without any tiles
+    if map.width == 0 || map.height == 0 {
+        anyhow::bail!("Map is {}x{}, so it has no tiles to write", map.width, map.height);
+    }
+    let mut text = String::new();
+    if !map.name.is_empty() {
+        if map.name.contains(['\n', '\r']) || map.name.trim() != map.name {
+            anyhow::bail!("Map name '{}' has line breaks or surrounding whitespace", map.name);
+        }
+        text += &format!("@name {}\n", map.name);
+    }
+    if let Some(spawn) = map.spawn {
+        text += &format!("@spawn {} {}\n", spawn.x, spawn.y);
+    }
+    if let Some(tileset_path) = &map.tileset_path {
+        if tileset_path.is_empty() || tileset_path.contains(char::is_whitespace) {
+            anyhow::bail!("Tileset path '{tileset_path}' is empty or contains whitespace");
+        }
+        text += &format!("@tileset {tileset_path}\n");
+    }
+    for object in &map.objects {
+        if object.kind.is_empty() || object.kind.contains(char::is_whitespace) {
+            anyhow::bail!("Object kind '{}' is empty or contains whitespace", object.kind);
+        }
+        text += &format!("@object {} {} {}", object.kind, object.position.x, object.position.y);
+        for (key, value) in &object.properties {
+            if key.contains(char::is_whitespace) || value.contains(char::is_whitespace) {
+                anyhow::bail!("Property '{key}' of '{}' contains whitespace", object.kind);
+            }
+            if key.contains('=') {
+                anyhow::bail!("Property '{key}' of '{}' contains '='", object.kind);
+            }
+            text += &format!(" {key}={value}");
+        }
+        text += "\n";
+    }
+    for layer in &map.parallax {
+        if layer.texture.contains(char::is_whitespace) {
+            anyhow::bail!("Parallax image path '{}' contains whitespace", layer.texture);
+        }
+        text += &format!(
+            "@parallax {} scroll={},{} offset={},{}",
+            layer.texture, layer.scroll.x, layer.scroll.y, layer.offset.x, layer.offset.y
+        );
+        match (layer.repeat_x, layer.repeat_y) {
+            (true, true) => text += " repeat=xy",
+            (true, false) => text += " repeat=x",
+            (false, true) => text += " repeat=y",
+            (false, false) => {}
+        }
+        text += "\n";
+    }
+
+    // The layer directive can be left out for maps that only have a collision layer
+    let single_layer = map.layers().len() == 1;
+    for layer in map.layers() {
+        if !single_layer {
+            let kind = match layer.kind {
+                LayerKind::Background => "background",
+                LayerKind::Collision => "collision",
+                LayerKind::Foreground => "foreground",
+            };
+            text += &format!("@layer {kind}\n");
+        }
+        for (y, row) in layer.tiles.chunks(map.width).enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let glyph = table.glyphs.get(tile).ok_or_else(|| {
+                    anyhow::anyhow!("Tile {tile} at ({x}, {y}) has no glyph in the table")
+                })?;
+                text.push(*glyph);
+            }
+            text.push('\n');
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static MAP: &str = "\
+@name test map
+@spawn 50 60.5
+@tileset ../tileset.json
+@object cat 400 50 move_time=0.25
+@parallax ../sky.png scroll=0.5,0 offset=0,16 repeat=x
+@layer background
+QQQ
+...
+@layer collision
+...
+XXX
+";
+
+    fn error(text: &str, table: &GlyphTable) -> String {
+        format!("{:#}", compile(text, table).err().unwrap())
+    }
+
+    #[test]
+    fn round_trips_ascii_maps() {
+        let table = GlyphTable::default_table();
+        let map = compile(MAP, &table).unwrap();
+        assert_eq!(map.name, "test map");
+        assert_eq!(map.spawn, Some(Vec2::new(50.0, 60.5)));
+        assert_eq!((map.width, map.height), (3, 2));
+        assert_eq!(decompile(&map, &table).unwrap(), MAP);
+    }
+
+    #[test]
+    fn compiles_maps_with_windows_line_endings() {
+        let table = GlyphTable::default_table();
+        // The last line is left with a "\r" when the file doesn't end with a line break
+        let text = MAP.replace('\n', "\r\n");
+        let map = compile(text.strip_suffix('\n').unwrap(), &table).unwrap();
+        assert_eq!(map.name, "test map");
+        assert_eq!(map.tileset_path.as_deref(), Some("../tileset.json"));
+        assert_eq!(decompile(&map, &table).unwrap(), MAP);
+    }
+
+    #[test]
+    fn reports_where_rows_are_invalid() {
+        let table = GlyphTable::default_table();
+        assert_eq!(
+            error("...\n....\n", &table),
+            "line 2: row has 4 columns, but the map is 3 columns wide"
+        );
+        assert_eq!(error("...\n.~.\n", &table), "line 2, column 2: unknown glyph '~'");
+        assert_eq!(
+            error("@layer background\n...\n@layer collision\n", &table),
+            "Collision layer has 0 rows, but the map is 1 rows high"
+        );
+    }
+
+    #[test]
+    fn uses_custom_glyph_tables() {
+        let path = std::env::temp_dir().join(format!("glyphs-{}.txt", std::process::id()));
+        std::fs::write(&path, "// Empty space\n_ 0\n# 1\n~ 7\n").unwrap();
+        let table = GlyphTable::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let table = table.unwrap();
+
+        let map = compile("~#_\n", &table).unwrap();
+        assert_eq!(map.layers()[0].tiles, vec![7, 1, 0]);
+        assert_eq!(decompile(&map, &table).unwrap(), "~#_\n");
+        assert!(error("...\n", &table).contains("unknown glyph '.'"));
+    }
+
+    #[test]
+    fn refuses_to_decompile_empty_maps() {
+        let map = Map::new(0, 0, vec![], TileSet::empty());
+        let error = decompile(&map, &GlyphTable::default_table()).err().unwrap();
+        assert_eq!(error.to_string(), "Map is 0x0, so it has no tiles to write");
+    }
+
+    #[test]
+    fn refuses_to_decompile_maps_that_would_not_compile_the_same() {
+        let decompile_error = |change: fn(&mut Map)| {
+            let table = GlyphTable::default_table();
+            let mut map = compile(MAP, &table).unwrap();
+            change(&mut map);
+            decompile(&map, &table).err().unwrap().to_string()
+        };
+        assert_eq!(
+            decompile_error(|map| map.name = "two\nlines".into()),
+            "Map name 'two\nlines' has line breaks or surrounding whitespace"
+        );
+        assert_eq!(
+            decompile_error(|map| map.tileset_path = Some("my tiles.json".into())),
+            "Tileset path 'my tiles.json' is empty or contains whitespace"
+        );
+        assert_eq!(
+            decompile_error(|map| map.objects[0].kind = "big cat".into()),
+            "Object kind 'big cat' is empty or contains whitespace"
+        );
+        assert_eq!(
+            decompile_error(|map| map.objects[0].kind = String::new()),
+            "Object kind '' is empty or contains whitespace"
+        );
+        assert_eq!(
+            decompile_error(|map| {
+                map.objects[0].properties.insert("move_time".into(), "0.25\n@spawn".into());
+            }),
+            "Property 'move_time' of 'cat' contains whitespace"
+        );
+        assert_eq!(
+            decompile_error(|map| {
+                map.objects[0].properties.insert("a=b".into(), "c".into());
+            }),
+            "Property 'a=b' of 'cat' contains '='"
+        );
+    }
+}
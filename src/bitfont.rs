@@ -4,9 +4,11 @@ use macroquad::texture::Texture2D;
 use crate::sprite::Sprite;
 
 /// A bitfont that can be used for rendering text
+#[derive(Clone)]
 pub struct BitFont {
     ascii_offset: u8,
     sprite: Sprite,
+    texture: Texture2D,
 }
 
 impl BitFont {
@@ -29,13 +31,13 @@ impl BitFont {
         BitFont {
             ascii_offset,
             sprite: Sprite {
-                spritesheet: texture,
                 offset: UVec2::ZERO,
                 frame_width: char_width,
                 frame_height: char_height,
                 num_frames_x: num_chars,
                 num_frames_y: 1,
             },
+            texture,
         }
     }
 
@@ -56,9 +58,26 @@ impl BitFont {
             }
             else {
                 let char = char - self.ascii_offset;
-                self.sprite.draw([char as u32, 0].into(), pos, false);
+                self.sprite.draw(self.texture, [char as u32, 0].into(), pos, false);
                 pos.x += self.sprite.frame_width as f32;
             }
         }
     }
+
+    /// Gets the size of some text when it is drawn
+    /// # Arguments
+    /// `text` - The text to measure
+    /// # Return
+    /// Returns the width and height of the text in pixels
+    pub fn text_size(&self, text: &str) -> Vec2 {
+        // Carriage returns are discarded when drawing, so they don't take up any space
+        let line_lengths =
+            text.split('\n').map(|line| line.bytes().filter(|&c| c != b'\r').count());
+        let (columns, rows) =
+            line_lengths.fold((0, 0), |(columns, rows), len| (columns.max(len), rows + 1));
+        Vec2::new(
+            (columns as u32 * self.sprite.frame_width) as f32,
+            (rows * self.sprite.frame_height) as f32,
+        )
+    }
 }
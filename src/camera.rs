@@ -0,0 +1,245 @@
+use macroquad::prelude::{Rect, Vec2};
+
+/// A camera that follows a target around the map. The camera is updated along with the
+/// simulation, and interpolated between its last two positions when drawing.
+pub struct Camera {
+    /// The top left corner of the view
+    position: Vec2,
+    // The position of the view before the most recent update, used for interpolation
+    previous_position: Vec2,
+    /// The size of the view in pixels
+    view_size: Vec2,
+    /// The size of the map, which the view is kept inside
+    bounds: Vec2,
+    /// The point the view is centered on, before looking ahead
+    focus: Vec2,
+    /// The current offset of the focus in the direction the target is moving
+    look_offset: f32,
+    /// The size of the area around the focus that the target can move in without moving the focus
+    pub dead_zone: Vec2,
+    /// How far ahead of a moving target the camera looks (pixels)
+    pub look_ahead: f32,
+    /// How quickly the camera catches up to where it should be. Higher values are faster.
+    pub damping: f32,
+    shake: Shake,
+}
+
+/// A short shake of the view, which fades out over its duration
+struct Shake {
+    /// The furthest the view is moved along each axis (pixels)
+    intensity: f32,
+    duration: f32,
+    time_left: f32,
+}
+
+/// How many times per second the view moves while it is shaking
+static SHAKE_RATE: f32 = 30.0;
+
+impl Camera {
+    /// Creates a new camera
+    /// # Arguments
+    /// `view_size` - The size of the view in pixels
+    /// `bounds` - The size of the map, which the view is kept inside
+    /// `target` - The point to center the view on
+    pub fn new(view_size: Vec2, bounds: Vec2, target: Vec2) -> Self {
+        let mut camera = Self {
+            position: Vec2::ZERO,
+            previous_position: Vec2::ZERO,
+            view_size,
+            bounds,
+            focus: target,
+            look_offset: 0.0,
+            dead_zone: Vec2::new(64.0, 96.0),
+            look_ahead: 96.0,
+            damping: 8.0,
+            shake: Shake { intensity: 0.0, duration: 0.0, time_left: 0.0 },
+        };
+        camera.snap_to(target);
+        camera
+    }
+
+    /// Changes the size of the view, keeping it inside the map
+    pub fn set_view_size(&mut self, view_size: Vec2) {
+        if view_size != self.view_size {
+            self.view_size = view_size;
+            self.position = self.clamp(self.position);
+            self.previous_position = self.clamp(self.previous_position);
+        }
+    }
+
+    /// Centers the view on a point immediately, without smoothing
+    pub fn snap_to(&mut self, target: Vec2) {
+        self.focus = target;
+        self.look_offset = 0.0;
+        self.position = self.clamp(target - self.view_size / 2.0);
+        self.previous_position = self.position;
+    }
+
+    /// Moves the view towards a target
+    /// # Arguments
+    /// `target` - The point to follow
+    /// `velocity` - How fast the target is moving, which the camera looks ahead in the direction of
+    /// `secs` - The total seconds elapsed since the last update
+    pub fn follow(&mut self, target: Vec2, velocity: Vec2, secs: f32) {
+        self.previous_position = self.position;
+
+        // Only move the focus once the target leaves the dead zone
+        let half_zone = self.dead_zone / 2.0;
+        self.focus = self.focus.clamp(target - half_zone, target + half_zone);
+
+        // Smoothing factor that doesn't depend on the update rate
+        let blend = 1.0 - (-self.damping * secs).exp();
+        let look_goal =
+            if velocity.x.abs() > 0.1 { velocity.x.signum() * self.look_ahead } else { 0.0 };
+        self.look_offset += (look_goal - self.look_offset) * blend;
+
+        let goal = self.focus + Vec2::new(self.look_offset, 0.0) - self.view_size / 2.0;
+        self.position = self.clamp(self.position + (goal - self.position) * blend);
+
+        self.shake.time_left = (self.shake.time_left - secs).max(0.0);
+    }
+
+    /// Shakes the view
+    /// # Arguments
+    /// `intensity` - The furthest the view is moved along each axis (pixels)
+    /// `duration` - The number of seconds the shake lasts
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        self.shake = Shake { intensity, duration, time_left: duration };
+    }
+
+    /// Gets the area of the map that is visible
+    /// # Arguments
+    /// `alpha` - How far between the previous and current update to interpolate (0.0 to 1.0)
+    /// # Return
+    /// Returns the visible area, with its corner rounded to a whole pixel
+    pub fn viewport(&self, alpha: f32) -> Rect {
+        let position = self.previous_position.lerp(self.position, alpha) + self.shake_offset();
+        let position = position.round();
+        Rect::new(position.x, position.y, self.view_size.x, self.view_size.y)
+    }
+
+    /// Converts a point in the view, such as the mouse position, to a point in the map
+    /// # Arguments
+    /// `point` - The point relative to the top left of the view
+    /// `alpha` - How far between the previous and current update to interpolate (0.0 to 1.0)
+    pub fn to_world(&self, point: Vec2, alpha: f32) -> Vec2 {
+        point + self.viewport(alpha).point()
+    }
+
+    /// Converts a point in the map to a point in the view
+    /// # Arguments
+    /// `point` - The point in the map
+    /// `alpha` - How far between the previous and current update to interpolate (0.0 to 1.0)
+    pub fn to_view(&self, point: Vec2, alpha: f32) -> Vec2 {
+        point - self.viewport(alpha).point()
+    }
+
+    /// Gets how far the view is moved by shaking
+    fn shake_offset(&self) -> Vec2 {
+        if self.shake.time_left <= 0.0 {
+            return Vec2::ZERO;
+        }
+        let fade = self.shake.time_left / self.shake.duration;
+        let t = (self.shake.duration - self.shake.time_left) * SHAKE_RATE;
+        // Uneven frequencies on each axis, so that the shake doesn't look like it is moving in a
+        // straight line
+        Vec2::new((t * 2.9).sin(), (t * 3.7).cos()) * self.shake.intensity * fade
+    }
+
+    /// Keeps the view inside the map, centering it if the map is smaller than the view
+    fn clamp(&self, position: Vec2) -> Vec2 {
+        let clamp_axis = |position: f32, view: f32, bounds: f32| {
+            if view >= bounds {
+                (bounds - view) / 2.0
+            }
+            else {
+                position.clamp(0.0, bounds - view)
+            }
+        };
+        Vec2::new(
+            clamp_axis(position.x, self.view_size.x, self.bounds.x),
+            clamp_axis(position.y, self.view_size.y, self.bounds.y),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static VIEW: Vec2 = Vec2::new(200.0, 100.0);
+    static MAP: Vec2 = Vec2::new(1000.0, 500.0);
+    static TICK: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn stays_inside_the_map() {
+        let mut camera = Camera::new(VIEW, MAP, Vec2::ZERO);
+        assert_eq!(camera.viewport(1.0), Rect::new(0.0, 0.0, 200.0, 100.0));
+
+        camera.snap_to(MAP);
+        assert_eq!(camera.viewport(1.0), Rect::new(800.0, 400.0, 200.0, 100.0));
+        assert_eq!(camera.to_world(Vec2::new(10.0, 20.0), 1.0), Vec2::new(810.0, 420.0));
+        assert_eq!(camera.to_view(Vec2::new(810.0, 420.0), 1.0), Vec2::new(10.0, 20.0));
+
+        for _ in 0..600 {
+            camera.follow(Vec2::new(-500.0, 250.0), Vec2::ZERO, TICK);
+        }
+        assert_eq!(camera.viewport(1.0).x, 0.0);
+
+        // Maps smaller than the view are centered
+        camera.set_view_size(Vec2::new(1200.0, 100.0));
+        assert_eq!(camera.viewport(1.0).x, -100.0);
+    }
+
+    #[test]
+    fn target_can_move_inside_the_dead_zone() {
+        let start = Vec2::new(500.0, 250.0);
+        let mut camera = Camera::new(VIEW, MAP, start);
+        let viewport = camera.viewport(1.0);
+
+        let half_zone = camera.dead_zone / 2.0;
+        for _ in 0..60 {
+            camera.follow(start + half_zone, Vec2::ZERO, TICK);
+        }
+        assert_eq!(camera.viewport(1.0), viewport);
+
+        for _ in 0..600 {
+            camera.follow(start + half_zone + Vec2::new(50.0, 0.0), Vec2::ZERO, TICK);
+        }
+        assert_eq!(camera.viewport(1.0).x, viewport.x + 50.0);
+    }
+
+    #[test]
+    fn smoothly_looks_ahead_of_moving_targets() {
+        let start = Vec2::new(500.0, 250.0);
+        let mut camera = Camera::new(VIEW, MAP, start);
+        let viewport = camera.viewport(1.0);
+
+        // The camera moves a little at a time
+        camera.follow(start, Vec2::new(5.0, 0.0), TICK);
+        let moved = camera.viewport(1.0).x - viewport.x;
+        assert!(moved > 0.0 && moved < camera.look_ahead / 2.0, "{moved}");
+        assert_eq!(camera.viewport(0.0), viewport);
+
+        for _ in 0..600 {
+            camera.follow(start, Vec2::new(5.0, 0.0), TICK);
+        }
+        assert_eq!(camera.viewport(1.0).x, viewport.x + camera.look_ahead);
+    }
+
+    #[test]
+    fn shakes_fade_out() {
+        let mut camera = Camera::new(VIEW, MAP, Vec2::new(500.0, 250.0));
+        let viewport = camera.viewport(1.0);
+        camera.shake(10.0, 0.5);
+        camera.follow(Vec2::new(500.0, 250.0), Vec2::ZERO, TICK);
+        assert_ne!(camera.viewport(1.0), viewport);
+        let moved = (camera.viewport(1.0).point() - viewport.point()).abs();
+        assert!(moved.max_element() <= 10.0, "{moved}");
+
+        for _ in 0..30 {
+            camera.follow(Vec2::new(500.0, 250.0), Vec2::ZERO, TICK);
+        }
+        assert_eq!(camera.viewport(1.0), viewport);
+    }
+}
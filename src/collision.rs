@@ -0,0 +1,493 @@
+//! Collision detection for boxes moving through the map, and contacts between the hitboxes of
+//! entities
+
+use std::collections::{BTreeSet, HashMap};
+
+use macroquad::prelude::{Rect, Vec2};
+
+use crate::{
+    map::Map,
+    tiles::{TileCollision, TileInfo},
+};
+
+/// The first contact made by a box moving through the map
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+    /// How far through the motion the contact is made (0.0 to 1.0)
+    pub time: f32,
+    /// The normal of the surface that was hit, pointing back towards the moving box
+    pub normal: Vec2,
+    /// The tile that was hit, or `None` if the box hit the edge of the map
+    pub tile: Option<(usize, usize)>,
+}
+
+/// The tile info used for the area outside of the map, which nothing can leave
+static OUTSIDE_MAP: TileInfo = TileInfo { collision: TileCollision::Solid, friction: 1.0 };
+
+/// Sweeps a box through the map, finding the first tile it hits. Only the tiles covered by the
+/// swept area are checked. Tiles the box already overlaps are ignored, so that objects are able to
+/// move out of them, and empty tiles never block the box.
+/// # Arguments
+/// `map` - The map to move through
+/// `bounds` - The box at the start of the motion
+/// `motion` - The distance to move the box
+/// `blocks` - Decides whether a tile blocks the box, given the tile info and the normal of the
+/// contact
+/// # Return
+/// Returns the earliest contact with a blocking tile, or `None` if the box can move the whole way
+pub fn sweep(
+    map: &Map,
+    bounds: Rect,
+    motion: Vec2,
+    blocks: impl Fn(TileInfo, Vec2) -> bool,
+) -> Option<Hit> {
+    if motion == Vec2::ZERO {
+        return None;
+    }
+
+    let tile_size = map.tile_size() as f32;
+    let swept = bounds.combine_with(bounds.offset(motion));
+    // Tiles more than one tile outside of the map can't be reached without passing through the
+    // tiles at the edge of the map first
+    let tile_range = |min: f32, max: f32, length: usize| {
+        let first = ((min / tile_size).floor() as i64).max(-1);
+        let last = ((max / tile_size).floor() as i64).min(length as i64);
+        first..=last
+    };
+
+    let mut first_hit: Option<Hit> = None;
+    let mut first_hit_corner = false;
+    for tile_y in tile_range(swept.top(), swept.bottom(), map.height) {
+        for tile_x in tile_range(swept.left(), swept.right(), map.width) {
+            let inside_map = tile_x >= 0
+                && tile_y >= 0
+                && (tile_x as usize) < map.width
+                && (tile_y as usize) < map.height;
+            let (info, tile) = if inside_map {
+                let tile = (tile_x as usize, tile_y as usize);
+                (map.tile_info_at(tile.0, tile.1), Some(tile))
+            }
+            else {
+                (OUTSIDE_MAP, None)
+            };
+            if info.collision == TileCollision::None {
+                continue;
+            }
+
+            let tile_rect = Rect::new(
+                tile_x as f32 * tile_size,
+                tile_y as f32 * tile_size,
+                tile_size,
+                tile_size,
+            );
+            let Some((time, normal, corner)) = sweep_rect(bounds, motion, tile_rect)
+            else {
+                continue;
+            };
+            // Hitting the side of a tile takes priority over hitting the corner of another tile at
+            // the same time, so that walls made of several tiles are flat
+            let earlier = first_hit.is_none_or(|hit| {
+                time < hit.time || (time == hit.time && first_hit_corner && !corner)
+            });
+            if earlier && blocks(info, normal) {
+                first_hit = Some(Hit { time, normal, tile });
+                first_hit_corner = corner;
+            }
+        }
+    }
+    first_hit
+}
+
+/// Finds when a moving box first touches a stationary box. Boxes that only touch along an edge
+/// or at a corner while moving past each other don't make contact.
+/// # Arguments
+/// `moving` - The moving box at the start of the motion
+/// `motion` - The distance the box moves
+/// `target` - The stationary box
+/// # Return
+/// Returns the time of the contact (0.0 to 1.0), the normal of the target's surface, and whether
+/// the contact is exactly on a corner, or `None` if the boxes don't make contact during the motion
+/// or already overlap
+pub fn sweep_rect(moving: Rect, motion: Vec2, target: Rect) -> Option<(f32, Vec2, bool)> {
+    if overlaps(moving, target) {
+        return None;
+    }
+
+    let (entry_x, exit_x) =
+        axis_times(moving.left(), moving.right(), target.left(), target.right(), motion.x)?;
+    let (entry_y, exit_y) =
+        axis_times(moving.top(), moving.bottom(), target.top(), target.bottom(), motion.y)?;
+    let entry = entry_x.max(entry_y);
+    let exit = exit_x.min(exit_y);
+    if entry >= exit || !(0.0..=1.0).contains(&entry) {
+        return None;
+    }
+
+    // The contact is on the side of the last axis to start overlapping. Hitting a corner exactly
+    // counts as hitting the top or bottom, so objects land on the corners of tiles.
+    let normal = if entry_x > entry_y {
+        Vec2::new(-motion.x.signum(), 0.0)
+    }
+    else {
+        Vec2::new(0.0, -motion.y.signum())
+    };
+    Some((entry, normal, entry_x == entry_y))
+}
+
+/// Checks if two boxes overlap. Boxes that only share an edge don't overlap.
+pub fn overlaps(a: Rect, b: Rect) -> bool {
+    a.left() < b.right() && a.right() > b.left() && a.top() < b.bottom() && a.bottom() > b.top()
+}
+
+/// Calculates when a moving interval starts and stops overlapping a stationary interval
+/// # Return
+/// Returns the entry and exit times, or `None` if the intervals never overlap
+fn axis_times(
+    min: f32,
+    max: f32,
+    target_min: f32,
+    target_max: f32,
+    motion: f32,
+) -> Option<(f32, f32)> {
+    if motion > 0.0 {
+        Some(((target_min - max) / motion, (target_max - min) / motion))
+    }
+    else if motion < 0.0 {
+        Some(((target_max - min) / motion, (target_min - max) / motion))
+    }
+    else if max > target_min && min < target_max {
+        // The intervals overlap for the whole motion
+        Some((f32::NEG_INFINITY, f32::INFINITY))
+    }
+    else {
+        None
+    }
+}
+
+/// A change in whether two hitboxes are touching. The ids in each event are in ascending order.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ContactEvent<Id> {
+    /// The hitboxes started overlapping during the last update
+    Begin(Id, Id),
+    /// The hitboxes stopped overlapping during the last update
+    End(Id, Id),
+}
+
+/// Keeps track of which hitboxes are touching between updates
+pub struct Contacts<Id> {
+    /// The size of the cells used to find hitboxes that are near each other
+    cell_size: f32,
+    /// The pairs of hitboxes touching after the last update, with the lower id first
+    touching: BTreeSet<(Id, Id)>,
+}
+
+impl<Id: Copy + Ord> Contacts<Id> {
+    /// Creates a contact tracker with no contacts
+    /// # Arguments
+    /// `cell_size` - The size of the cells of the spatial hash used to find hitboxes that are near
+    /// each other. This works best when it is around the size of a typical hitbox.
+    pub fn new(cell_size: f32) -> Self {
+        Self { cell_size, touching: BTreeSet::new() }
+    }
+
+    /// Finds the hitboxes that are touching, and compares them to the last update
+    /// # Arguments
+    /// `hitboxes` - The hitboxes of every live entity, each with a unique id
+    /// # Return
+    /// Returns the contacts that began or ended since the last update, ordered by id
+    pub fn update(&mut self, hitboxes: &[(Id, Rect)]) -> Vec<ContactEvent<Id>> {
+        let touching: BTreeSet<(Id, Id)> = self
+            .candidate_pairs(hitboxes)
+            .into_iter()
+            .filter(|&(a, b)| overlaps(hitboxes[a].1, hitboxes[b].1))
+            .map(|(a, b)| {
+                let (a, b) = (hitboxes[a].0, hitboxes[b].0);
+                if a < b {
+                    (a, b)
+                }
+                else {
+                    (b, a)
+                }
+            })
+            .collect();
+
+        let ended = self.touching.difference(&touching).map(|&(a, b)| ContactEvent::End(a, b));
+        let began = touching.difference(&self.touching).map(|&(a, b)| ContactEvent::Begin(a, b));
+        let events = ended.chain(began).collect();
+        self.touching = touching;
+        events
+    }
+
+    /// Checks if two hitboxes were touching after the last update
+    pub fn is_touching(&self, a: Id, b: Id) -> bool {
+        self.touching.contains(&if a < b { (a, b) } else { (b, a) })
+    }
+
+    /// Gets the hitboxes that were touching a hitbox after the last update
+    pub fn touching(&self, id: Id) -> impl Iterator<Item = Id> + '_ {
+        self.touching.iter().filter_map(move |&(a, b)| {
+            if a == id {
+                Some(b)
+            }
+            else if b == id {
+                Some(a)
+            }
+            else {
+                None
+            }
+        })
+    }
+
+    /// Finds the pairs of hitboxes that share a cell of the spatial hash, which are the only
+    /// hitboxes that can be touching
+    /// # Return
+    /// Returns pairs of indices into `hitboxes`, with the lower index first
+    fn candidate_pairs(&self, hitboxes: &[(Id, Rect)]) -> BTreeSet<(usize, usize)> {
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, (_, hitbox)) in hitboxes.iter().enumerate() {
+            let cell = |x: f32| (x / self.cell_size).floor() as i64;
+            for y in cell(hitbox.top())..=cell(hitbox.bottom()) {
+                for x in cell(hitbox.left())..=cell(hitbox.right()) {
+                    cells.entry((x, y)).or_default().push(i);
+                }
+            }
+        }
+
+        let mut pairs = BTreeSet::new();
+        for cell in cells.values() {
+            for (n, &a) in cell.iter().enumerate() {
+                pairs.extend(cell[n + 1..].iter().map(|&b| (a, b)));
+            }
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::fixtures;
+
+    static MAP_SIZE: usize = 8;
+    static TILE_SIZE: f32 = 32.0;
+
+    fn solid(info: TileInfo, _: Vec2) -> bool {
+        info.collision == TileCollision::Solid
+    }
+
+    /// Creates a map from a list of which tiles are solid
+    fn map(solid: &[bool]) -> Map {
+        fixtures::map(MAP_SIZE, MAP_SIZE, |x, y| solid[x + y * MAP_SIZE])
+    }
+
+    fn tile_rect(x: i64, y: i64) -> Rect {
+        Rect::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE, TILE_SIZE, TILE_SIZE)
+    }
+
+    /// Gets the solid tiles of the map, including the tiles around the outside of it
+    fn solid_tiles(map: &Map) -> impl Iterator<Item = Rect> + '_ {
+        let range = -1..=MAP_SIZE as i64;
+        range.clone().flat_map(move |y| range.clone().map(move |x| (x, y))).filter_map(|(x, y)| {
+            let inside = (0..MAP_SIZE as i64).contains(&x) && (0..MAP_SIZE as i64).contains(&y);
+            let solid = !inside
+                || map.tile_info_at(x as usize, y as usize).collision != TileCollision::None;
+            solid.then(|| tile_rect(x, y))
+        })
+    }
+
+    /// Checks if a box overlaps a solid tile by more than a small tolerance for rounding errors
+    fn blocked(map: &Map, bounds: Rect) -> bool {
+        let shrunk = Rect::new(bounds.x + 0.01, bounds.y + 0.01, bounds.w - 0.02, bounds.h - 0.02);
+        solid_tiles(map).any(|tile| overlaps(shrunk, tile))
+    }
+
+    /// A position that is often exactly on the edge of a tile
+    fn coordinate() -> impl Strategy<Value = f32> {
+        let max = MAP_SIZE as f32 * TILE_SIZE;
+        prop_oneof![(0..=MAP_SIZE as i32).prop_map(|tile| tile as f32 * TILE_SIZE), 0.0..max]
+    }
+
+    fn size() -> impl Strategy<Value = f32> {
+        prop_oneof![Just(TILE_SIZE), Just(TILE_SIZE / 2.0), 1.0f32..80.0]
+    }
+
+    /// A distance that is often zero or a whole number of tiles
+    fn distance() -> impl Strategy<Value = f32> {
+        prop_oneof![
+            Just(0.0),
+            (-3..=3).prop_map(|tiles| tiles as f32 * TILE_SIZE),
+            -100.0f32..100.0
+        ]
+    }
+
+    /// The solid tiles of a map, and a box inside the map that doesn't overlap any of them
+    fn scene() -> impl Strategy<Value = (Vec<bool>, Rect)> {
+        let tiles = prop::collection::vec(prop::bool::weighted(0.3), MAP_SIZE * MAP_SIZE);
+        (tiles, coordinate(), coordinate(), size(), size()).prop_map(|(mut tiles, x, y, w, h)| {
+            let max = MAP_SIZE as f32 * TILE_SIZE;
+            let bounds = Rect::new(x.min(max - w), y.min(max - h), w, h);
+            // Clear the tiles under the box
+            for (i, solid) in tiles.iter_mut().enumerate() {
+                let tile = tile_rect((i % MAP_SIZE) as i64, (i / MAP_SIZE) as i64);
+                *solid &= !overlaps(bounds, tile);
+            }
+            (tiles, bounds)
+        })
+    }
+
+    fn motion() -> impl Strategy<Value = Vec2> {
+        (distance(), distance()).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn hitbox() -> impl Strategy<Value = Rect> {
+        (-200.0f32..200.0, -200.0f32..200.0, size(), size())
+            .prop_map(|(x, y, w, h)| Rect::new(x, y, w, h))
+    }
+
+    proptest! {
+        #[test]
+        fn finds_every_pair_of_touching_hitboxes(
+            hitboxes in prop::collection::vec(hitbox(), 0..20),
+        ) {
+            let hitboxes: Vec<(usize, Rect)> = hitboxes.into_iter().enumerate().collect();
+            let mut expected = vec![];
+            for (a, &(_, first)) in hitboxes.iter().enumerate() {
+                for (b, &(_, second)) in hitboxes.iter().enumerate().skip(a + 1) {
+                    if overlaps(first, second) {
+                        expected.push(ContactEvent::Begin(a, b));
+                    }
+                }
+            }
+            let mut contacts = Contacts::new(TILE_SIZE);
+            prop_assert_eq!(contacts.update(&hitboxes), expected);
+            // Nothing changed, so there are no new events
+            prop_assert_eq!(contacts.update(&hitboxes), vec![]);
+        }
+
+        #[test]
+        fn never_passes_through_solid_tiles((tiles, bounds) in scene(), motion in motion()) {
+            let map = map(&tiles);
+            let end = sweep(&map, bounds, motion, solid).map_or(1.0, |hit| hit.time);
+            for step in 0..=100 {
+                let moved = bounds.offset(motion * end * step as f32 / 100.0);
+                prop_assert!(!blocked(&map, moved), "{:?} is inside a solid tile", moved);
+            }
+        }
+
+        #[test]
+        fn hits_are_contacts((tiles, bounds) in scene(), motion in motion()) {
+            let map = map(&tiles);
+            let Some(hit) = sweep(&map, bounds, motion, solid) else { return Ok(()) };
+            prop_assert!((0.0..=1.0).contains(&hit.time));
+            // The normal is along one axis, against the motion
+            prop_assert!(hit.normal.x == 0.0 || hit.normal.y == 0.0);
+            prop_assert_eq!(hit.normal.length(), 1.0);
+            prop_assert!(hit.normal.dot(motion) < 0.0);
+
+            let moved = bounds.offset(motion * hit.time);
+            match hit.tile {
+                Some((x, y)) => {
+                    let tile = tile_rect(x as i64, y as i64);
+                    // The tile is inside the area swept by the box
+                    let swept = bounds.combine_with(bounds.offset(motion));
+                    prop_assert!(swept.overlaps(&tile));
+
+                    // At the time of impact the box is touching the tile on the side of the normal
+                    let gap =
+                        (moved.center() - tile.center()).abs() - (moved.size() + tile.size()) / 2.0;
+                    let (along, across) =
+                        if hit.normal.x != 0.0 { (gap.x, gap.y) } else { (gap.y, gap.x) };
+                    prop_assert!(along.abs() < 0.01, "gap along the normal: {}", along);
+                    prop_assert!(across < 0.01, "gap across the normal: {}", across);
+                }
+                None => {
+                    // The box is touching the edge of the map
+                    let max = MAP_SIZE as f32 * TILE_SIZE;
+                    let edge = match (hit.normal.x, hit.normal.y) {
+                        (x, _) if x > 0.0 => moved.left(),
+                        (x, _) if x < 0.0 => moved.right() - max,
+                        (_, y) if y > 0.0 => moved.top(),
+                        _ => moved.bottom() - max,
+                    };
+                    prop_assert!(edge.abs() < 0.01, "distance from the edge: {}", edge);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn touching_a_tile_while_moving_into_it_hits_immediately() {
+        let mut tiles = vec![false; MAP_SIZE * MAP_SIZE];
+        tiles[2 * MAP_SIZE + 1] = true;
+        let map = map(&tiles);
+        let hit = sweep(&map, Rect::new(32.0, 32.0, 32.0, 32.0), Vec2::new(0.0, 10.0), solid);
+        assert_eq!(hit, Some(Hit { time: 0.0, normal: Vec2::new(0.0, -1.0), tile: Some((1, 2)) }));
+    }
+
+    #[test]
+    fn sliding_along_tiles_does_not_hit_them() {
+        let mut tiles = vec![false; MAP_SIZE * MAP_SIZE];
+        tiles[2 * MAP_SIZE..3 * MAP_SIZE].fill(true);
+        let map = map(&tiles);
+        let bounds = Rect::new(32.0, 32.0, 32.0, 32.0);
+        assert_eq!(sweep(&map, bounds, Vec2::new(64.0, 0.0), solid), None);
+        assert_eq!(sweep(&map, bounds, Vec2::new(-16.0, 0.0), solid), None);
+    }
+
+    #[test]
+    fn hitting_a_corner_lands_on_it() {
+        let mut tiles = vec![false; MAP_SIZE * MAP_SIZE];
+        tiles[2 * MAP_SIZE + 2] = true;
+        let map = map(&tiles);
+        let hit = sweep(&map, Rect::new(32.0, 32.0, 32.0, 32.0), Vec2::new(10.0, 10.0), solid);
+        assert_eq!(hit, Some(Hit { time: 0.0, normal: Vec2::new(0.0, -1.0), tile: Some((2, 2)) }));
+    }
+
+    #[test]
+    fn edge_of_map_is_solid() {
+        let map = map(&vec![false; MAP_SIZE * MAP_SIZE]);
+        let hit = sweep(&map, Rect::new(10.0, 10.0, 32.0, 32.0), Vec2::new(-50.0, 0.0), solid);
+        assert_eq!(hit, Some(Hit { time: 0.2, normal: Vec2::new(1.0, 0.0), tile: None }));
+    }
+
+    #[test]
+    fn boxes_outside_of_the_map_can_move_back_in() {
+        let map = map(&vec![false; MAP_SIZE * MAP_SIZE]);
+        let bounds = Rect::new(40.0, -50.0, 32.0, 32.0);
+        assert_eq!(sweep(&map, bounds, Vec2::new(0.0, 60.0), solid), None);
+    }
+
+    #[test]
+    fn walls_are_flat_where_tiles_meet() {
+        let map = map(&vec![false; MAP_SIZE * MAP_SIZE]);
+        // The box is touching the left edge of the map, where two tiles outside of the map meet
+        let hit = sweep(&map, Rect::new(0.0, 32.0, 32.0, 32.0), Vec2::new(-10.0, -10.0), solid);
+        assert_eq!(hit, Some(Hit { time: 0.0, normal: Vec2::new(1.0, 0.0), tile: None }));
+    }
+
+    #[test]
+    fn contacts_begin_and_end() {
+        let mut contacts = Contacts::new(TILE_SIZE);
+        let player = Rect::new(0.0, 0.0, 32.0, 64.0);
+        let cat = |x| ("cat", Rect::new(x, 32.0, 32.0, 32.0));
+        let lava = ("lava", Rect::new(100.0, 48.0, 32.0, 32.0));
+
+        assert_eq!(contacts.update(&[("player", player), cat(40.0), lava]), vec![]);
+        // Only sharing an edge isn't a contact
+        assert_eq!(contacts.update(&[("player", player), cat(32.0), lava]), vec![]);
+        assert_eq!(contacts.update(&[("player", player), cat(20.0), lava]), vec![
+            ContactEvent::Begin("cat", "player")
+        ]);
+        assert!(contacts.is_touching("player", "cat"));
+        assert_eq!(contacts.update(&[("player", player), cat(10.0), lava]), vec![]);
+        assert_eq!(contacts.update(&[("player", player), cat(90.0), lava]), vec![
+            ContactEvent::End("cat", "player"),
+            ContactEvent::Begin("cat", "lava"),
+        ]);
+        assert!(!contacts.is_touching("player", "cat"));
+        // Entities that are removed stop touching
+        assert_eq!(contacts.update(&[("player", player)]), vec![ContactEvent::End("cat", "lava")]);
+    }
+}
@@ -0,0 +1,622 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::Path,
+    rc::Rc,
+    str::FromStr,
+};
+
+use anyhow::Context;
+use macroquad::input::KeyCode;
+use serde::Deserialize;
+
+/// The things the player can do, which are bound to keys and gamepad controls
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    /// Drop through one-way platforms
+    Down,
+    Pause,
+    /// Accept the choice on a menu
+    Confirm,
+    Quit,
+}
+
+/// A button on a gamepad, named by its position so that it is the same on every layout
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// An axis on a gamepad. Values are between -1.0 and 1.0, and Y axes are positive upwards.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+/// The direction an axis must be pushed in to activate a binding
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+/// A control that activates an action
+#[derive(Copy, Clone, PartialEq, Debug, Deserialize)]
+#[serde(try_from = "RawBinding")]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButton),
+    Axis(GamepadAxis, AxisDirection),
+}
+
+/// A binding as it is written in the config file, with exactly one of `key`, `button` or `axis`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBinding {
+    key: Option<String>,
+    button: Option<GamepadButton>,
+    axis: Option<GamepadAxis>,
+    direction: Option<AxisDirection>,
+}
+
+impl TryFrom<RawBinding> for Binding {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawBinding) -> Result<Self, Self::Error> {
+        match raw {
+            RawBinding { key: Some(key), button: None, axis: None, direction: None } => {
+                Ok(Binding::Key(parse_key(&key)?))
+            }
+            RawBinding { key: None, button: Some(button), axis: None, direction: None } => {
+                Ok(Binding::Button(button))
+            }
+            RawBinding { key: None, button: None, axis: Some(axis), direction } => {
+                Ok(Binding::Axis(axis, direction.context("Axis bindings need a direction")?))
+            }
+            _ => anyhow::bail!("Bindings need exactly one of 'key', 'button' or 'axis'"),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use GamepadButton::*;
+        match *self {
+            // Key names are the same as in the config file
+            Binding::Key(key) => write!(f, "{key:?}"),
+            Binding::Button(button) => f.write_str(match button {
+                South => "South button",
+                East => "East button",
+                North => "North button",
+                West => "West button",
+                LeftBumper => "Left bumper",
+                RightBumper => "Right bumper",
+                LeftTrigger => "Left trigger",
+                RightTrigger => "Right trigger",
+                Select => "Select",
+                Start => "Start",
+                DPadUp => "D-pad up",
+                DPadDown => "D-pad down",
+                DPadLeft => "D-pad left",
+                DPadRight => "D-pad right",
+            }),
+            Binding::Axis(axis, direction) => f.write_str(match (axis, direction) {
+                (GamepadAxis::LeftStickX, AxisDirection::Negative) => "Left stick left",
+                (GamepadAxis::LeftStickX, AxisDirection::Positive) => "Left stick right",
+                (GamepadAxis::LeftStickY, AxisDirection::Negative) => "Left stick down",
+                (GamepadAxis::LeftStickY, AxisDirection::Positive) => "Left stick up",
+                (GamepadAxis::RightStickX, AxisDirection::Negative) => "Right stick left",
+                (GamepadAxis::RightStickX, AxisDirection::Positive) => "Right stick right",
+                (GamepadAxis::RightStickY, AxisDirection::Negative) => "Right stick down",
+                (GamepadAxis::RightStickY, AxisDirection::Positive) => "Right stick up",
+            }),
+        }
+    }
+}
+
+/// Parses the name of a key, which is the same as its name in `KeyCode`
+fn parse_key(name: &str) -> anyhow::Result<KeyCode> {
+    let key = match name {
+        "Space" => KeyCode::Space,
+        "Enter" => KeyCode::Enter,
+        "Escape" => KeyCode::Escape,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "LeftShift" => KeyCode::LeftShift,
+        "RightShift" => KeyCode::RightShift,
+        "LeftControl" => KeyCode::LeftControl,
+        "RightControl" => KeyCode::RightControl,
+        "LeftAlt" => KeyCode::LeftAlt,
+        "RightAlt" => KeyCode::RightAlt,
+        "A" => KeyCode::A,
+        "B" => KeyCode::B,
+        "C" => KeyCode::C,
+        "D" => KeyCode::D,
+        "E" => KeyCode::E,
+        "F" => KeyCode::F,
+        "G" => KeyCode::G,
+        "H" => KeyCode::H,
+        "I" => KeyCode::I,
+        "J" => KeyCode::J,
+        "K" => KeyCode::K,
+        "L" => KeyCode::L,
+        "M" => KeyCode::M,
+        "N" => KeyCode::N,
+        "O" => KeyCode::O,
+        "P" => KeyCode::P,
+        "Q" => KeyCode::Q,
+        "R" => KeyCode::R,
+        "S" => KeyCode::S,
+        "T" => KeyCode::T,
+        "U" => KeyCode::U,
+        "V" => KeyCode::V,
+        "W" => KeyCode::W,
+        "X" => KeyCode::X,
+        "Y" => KeyCode::Y,
+        "Z" => KeyCode::Z,
+        "Key0" => KeyCode::Key0,
+        "Key1" => KeyCode::Key1,
+        "Key2" => KeyCode::Key2,
+        "Key3" => KeyCode::Key3,
+        "Key4" => KeyCode::Key4,
+        "Key5" => KeyCode::Key5,
+        "Key6" => KeyCode::Key6,
+        "Key7" => KeyCode::Key7,
+        "Key8" => KeyCode::Key8,
+        "Key9" => KeyCode::Key9,
+        _ => anyhow::bail!("Unknown key '{name}'"),
+    };
+    Ok(key)
+}
+
+/// The controls bound to each action
+#[derive(Clone, Debug, Deserialize)]
+pub struct Bindings {
+    /// How far an axis must be pushed before it activates a binding (0.0 to 1.0)
+    #[serde(default = "default_deadzone")]
+    pub deadzone: f32,
+    pub actions: HashMap<Action, Vec<Binding>>,
+}
+
+fn default_deadzone() -> f32 {
+    0.3
+}
+
+impl Bindings {
+    /// Loads bindings from a JSON config file
+    /// # Arguments
+    /// `path` - The path of the config file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open: {}", path.display()))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("invalid bindings: {}", path.display()))
+    }
+
+    /// Names the controls bound to an action, to show the player which ones to use
+    /// # Arguments
+    /// `action` - The action to describe
+    pub fn describe(&self, action: Action) -> String {
+        match self.actions.get(&action) {
+            Some(bindings) if !bindings.is_empty() => {
+                bindings.iter().map(Binding::to_string).collect::<Vec<_>>().join(" / ")
+            }
+            _ => "(unbound)".to_string(),
+        }
+    }
+
+    /// Checks if any of the controls bound to an action are active
+    /// # Arguments
+    /// `action` - The action to check
+    /// `source` - The state of the input devices
+    pub fn is_active(&self, action: Action, source: &dyn InputSource) -> bool {
+        let bindings = self.actions.get(&action).map_or(&[][..], |bindings| bindings);
+        bindings.iter().any(|&binding| match binding {
+            Binding::Key(key) => source.is_key_down(key),
+            Binding::Button(button) => source.is_button_down(button),
+            Binding::Axis(axis, AxisDirection::Positive) => source.axis(axis) > self.deadzone,
+            Binding::Axis(axis, AxisDirection::Negative) => source.axis(axis) < -self.deadzone,
+        })
+    }
+}
+
+impl FromStr for Bindings {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+        let actions = [
+            (Action::MoveLeft, vec![
+                Key(KeyCode::Left),
+                Key(KeyCode::A),
+                Button(GamepadButton::DPadLeft),
+                Axis(GamepadAxis::LeftStickX, AxisDirection::Negative),
+            ]),
+            (Action::MoveRight, vec![
+                Key(KeyCode::Right),
+                Key(KeyCode::D),
+                Button(GamepadButton::DPadRight),
+                Axis(GamepadAxis::LeftStickX, AxisDirection::Positive),
+            ]),
+            (Action::Jump, vec![
+                Key(KeyCode::Up),
+                Key(KeyCode::W),
+                Key(KeyCode::Space),
+                Button(GamepadButton::South),
+            ]),
+            (Action::Down, vec![
+                Key(KeyCode::Down),
+                Key(KeyCode::S),
+                Button(GamepadButton::DPadDown),
+                Axis(GamepadAxis::LeftStickY, AxisDirection::Negative),
+            ]),
+            (Action::Pause, vec![Key(KeyCode::Escape), Button(GamepadButton::Start)]),
+            (Action::Confirm, vec![Key(KeyCode::Enter), Button(GamepadButton::South)]),
+            (Action::Quit, vec![Key(KeyCode::Q), Button(GamepadButton::Select)]),
+        ];
+        Self { deadzone: default_deadzone(), actions: actions.into_iter().collect() }
+    }
+}
+
+/// The state of the keyboard and gamepads
+pub trait InputSource {
+    /// Reads any new input from the devices. This is called before the state is checked.
+    fn update(&mut self) {}
+
+    fn is_key_down(&self, key: KeyCode) -> bool;
+
+    fn is_button_down(&self, button: GamepadButton) -> bool;
+
+    /// Gets the position of an axis, between -1.0 and 1.0
+    fn axis(&self, axis: GamepadAxis) -> f32;
+}
+
+/// Reads input from the real keyboard, and from gamepads when the `gamepad` feature is enabled
+pub struct DeviceInput {
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<gilrs::Gilrs>,
+}
+
+impl DeviceInput {
+    pub fn new() -> Self {
+        Self {
+            // The game is still playable with the keyboard if gamepads aren't supported
+            #[cfg(feature = "gamepad")]
+            gamepads: gilrs::Gilrs::new().ok(),
+        }
+    }
+}
+
+impl Default for DeviceInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl DeviceInput {
+    /// Checks each connected gamepad
+    fn any_gamepad(&self, check: impl Fn(gilrs::Gamepad) -> bool) -> bool {
+        self.gamepads.as_ref().is_some_and(|gilrs| gilrs.gamepads().any(|(_, pad)| check(pad)))
+    }
+}
+
+impl InputSource for DeviceInput {
+    #[cfg(feature = "gamepad")]
+    fn update(&mut self) {
+        // Gamepad state is only updated as its events are read
+        if let Some(gilrs) = &mut self.gamepads {
+            while gilrs.next_event().is_some() {}
+        }
+    }
+
+    fn is_key_down(&self, key: KeyCode) -> bool {
+        macroquad::input::is_key_down(key)
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn is_button_down(&self, button: GamepadButton) -> bool {
+        let button = match button {
+            GamepadButton::South => gilrs::Button::South,
+            GamepadButton::East => gilrs::Button::East,
+            GamepadButton::North => gilrs::Button::North,
+            GamepadButton::West => gilrs::Button::West,
+            GamepadButton::LeftBumper => gilrs::Button::LeftTrigger,
+            GamepadButton::RightBumper => gilrs::Button::RightTrigger,
+            GamepadButton::LeftTrigger => gilrs::Button::LeftTrigger2,
+            GamepadButton::RightTrigger => gilrs::Button::RightTrigger2,
+            GamepadButton::Select => gilrs::Button::Select,
+            GamepadButton::Start => gilrs::Button::Start,
+            GamepadButton::DPadUp => gilrs::Button::DPadUp,
+            GamepadButton::DPadDown => gilrs::Button::DPadDown,
+            GamepadButton::DPadLeft => gilrs::Button::DPadLeft,
+            GamepadButton::DPadRight => gilrs::Button::DPadRight,
+        };
+        self.any_gamepad(|pad| pad.is_pressed(button))
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    fn is_button_down(&self, _: GamepadButton) -> bool {
+        false
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn axis(&self, axis: GamepadAxis) -> f32 {
+        let axis = match axis {
+            GamepadAxis::LeftStickX => gilrs::Axis::LeftStickX,
+            GamepadAxis::LeftStickY => gilrs::Axis::LeftStickY,
+            GamepadAxis::RightStickX => gilrs::Axis::RightStickX,
+            GamepadAxis::RightStickY => gilrs::Axis::RightStickY,
+        };
+        // Use whichever gamepad has the axis pushed the furthest
+        let gilrs = match &self.gamepads {
+            Some(gilrs) => gilrs,
+            None => return 0.0,
+        };
+        gilrs
+            .gamepads()
+            .map(|(_, pad)| pad.value(axis))
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+            .unwrap_or(0.0)
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    fn axis(&self, _: GamepadAxis) -> f32 {
+        0.0
+    }
+}
+
+/// The state of a fake keyboard and gamepad. Clones share the same state, so a test can keep a
+/// clone to change the input after giving one to an `Input`.
+#[derive(Clone, Default)]
+pub struct FakeInput {
+    state: Rc<RefCell<FakeState>>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    keys: HashSet<KeyCode>,
+    buttons: HashSet<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+impl FakeInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_key(&self, key: KeyCode, down: bool) {
+        let keys = &mut self.state.borrow_mut().keys;
+        if down {
+            keys.insert(key);
+        }
+        else {
+            keys.remove(&key);
+        }
+    }
+
+    pub fn set_button(&self, button: GamepadButton, down: bool) {
+        let buttons = &mut self.state.borrow_mut().buttons;
+        if down {
+            buttons.insert(button);
+        }
+        else {
+            buttons.remove(&button);
+        }
+    }
+
+    pub fn set_axis(&self, axis: GamepadAxis, value: f32) {
+        self.state.borrow_mut().axes.insert(axis, value);
+    }
+}
+
+impl InputSource for FakeInput {
+    fn is_key_down(&self, key: KeyCode) -> bool {
+        self.state.borrow().keys.contains(&key)
+    }
+
+    fn is_button_down(&self, button: GamepadButton) -> bool {
+        self.state.borrow().buttons.contains(&button)
+    }
+
+    fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.state.borrow().axes.get(&axis).copied().unwrap_or(0.0)
+    }
+}
+
+/// Tracks which actions are active, using the bindings to read an input source
+pub struct Input {
+    bindings: Bindings,
+    source: Box<dyn InputSource>,
+    active: HashSet<Action>,
+    // The actions that were active before the last update, used to tell when they are pressed
+    previous: HashSet<Action>,
+}
+
+impl Input {
+    /// Creates a new input with no actions active until the first update
+    /// # Arguments
+    /// `bindings` - The controls bound to each action
+    /// `source` - Where to read the state of the controls from
+    pub fn new(bindings: Bindings, source: impl InputSource + 'static) -> Self {
+        Self {
+            bindings,
+            source: Box::new(source),
+            active: HashSet::new(),
+            previous: HashSet::new(),
+        }
+    }
+
+    /// Reads the current state of the input source
+    pub fn update(&mut self) {
+        self.source.update();
+        let active = self
+            .bindings
+            .actions
+            .keys()
+            .copied()
+            .filter(|&action| self.bindings.is_active(action, self.source.as_ref()))
+            .collect();
+        self.previous = std::mem::replace(&mut self.active, active);
+    }
+
+    /// Checks if an action is active
+    pub fn is_down(&self, action: Action) -> bool {
+        self.active.contains(&action)
+    }
+
+    /// Checks if an action became active in the last update
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.is_down(action) && !self.previous.contains(&action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_can_have_many_bindings() {
+        let fake = FakeInput::new();
+        let mut input = Input::new(Bindings::default(), fake.clone());
+
+        input.update();
+        assert!(!input.is_down(Action::MoveLeft));
+
+        fake.set_key(KeyCode::Left, true);
+        input.update();
+        assert!(input.is_down(Action::MoveLeft));
+
+        fake.set_key(KeyCode::Left, false);
+        fake.set_button(GamepadButton::DPadLeft, true);
+        input.update();
+        assert!(input.is_down(Action::MoveLeft));
+        assert!(!input.is_down(Action::MoveRight));
+    }
+
+    #[test]
+    fn axes_have_a_deadzone() {
+        let fake = FakeInput::new();
+        let mut input = Input::new(Bindings::default(), fake.clone());
+
+        fake.set_axis(GamepadAxis::LeftStickX, 0.2);
+        input.update();
+        assert!(!input.is_down(Action::MoveRight));
+
+        fake.set_axis(GamepadAxis::LeftStickX, 0.5);
+        input.update();
+        assert!(input.is_down(Action::MoveRight));
+        assert!(!input.is_down(Action::MoveLeft));
+
+        fake.set_axis(GamepadAxis::LeftStickX, -0.5);
+        input.update();
+        assert!(input.is_down(Action::MoveLeft));
+    }
+
+    #[test]
+    fn actions_are_pressed_once() {
+        let fake = FakeInput::new();
+        let mut input = Input::new(Bindings::default(), fake.clone());
+
+        fake.set_key(KeyCode::Escape, true);
+        input.update();
+        assert!(input.is_pressed(Action::Pause));
+        input.update();
+        assert!(input.is_down(Action::Pause));
+        assert!(!input.is_pressed(Action::Pause));
+    }
+
+    #[test]
+    fn loads_bindings_from_config() {
+        let bindings: Bindings = r#"{
+            "deadzone": 0.5,
+            "actions": {
+                "jump": [{ "key": "Space" }, { "key": "W" }, { "button": "east" }],
+                "move_left": [{ "axis": "right_stick_x", "direction": "negative" }]
+            }
+        }"#
+        .parse()
+        .unwrap();
+        assert_eq!(bindings.deadzone, 0.5);
+        assert_eq!(bindings.actions[&Action::Jump], [
+            Binding::Key(KeyCode::Space),
+            Binding::Key(KeyCode::W),
+            Binding::Button(GamepadButton::East)
+        ]);
+        assert_eq!(bindings.actions[&Action::MoveLeft], [Binding::Axis(
+            GamepadAxis::RightStickX,
+            AxisDirection::Negative
+        )]);
+
+        let invalid = [
+            r#"{ "actions": { "jump": [{ "key": "NotAKey" }] } }"#,
+            r#"{ "actions": { "jump": [{ "key": "Space", "button": "south" }] } }"#,
+            r#"{ "actions": { "jump": [{ "axis": "left_stick_y" }] } }"#,
+            r#"{ "actions": { "fly": [{ "key": "Space" }] } }"#,
+        ];
+        for config in invalid {
+            assert!(config.parse::<Bindings>().is_err(), "{config}");
+        }
+
+        // The default bindings are the same as the ones shipped with the game
+        let bindings = Bindings::load(Path::new("./assets/bindings.json")).unwrap();
+        let default = Bindings::default();
+        assert_eq!(bindings.deadzone, default.deadzone);
+        assert_eq!(bindings.actions, default.actions);
+    }
+
+    #[test]
+    fn describes_the_controls_bound_to_actions() {
+        let bindings = Bindings::default();
+        assert_eq!(bindings.describe(Action::Confirm), "Enter / South button");
+        assert_eq!(bindings.describe(Action::MoveLeft), "Left / A / D-pad left / Left stick left");
+
+        let bindings: Bindings = r#"{
+            "actions": {
+                "pause": [{ "key": "P" }, { "axis": "right_stick_y", "direction": "positive" }],
+                "quit": []
+            }
+        }"#
+        .parse()
+        .unwrap();
+        assert_eq!(bindings.describe(Action::Pause), "P / Right stick up");
+        assert_eq!(bindings.describe(Action::Quit), "(unbound)");
+        assert_eq!(bindings.describe(Action::Confirm), "(unbound)");
+    }
+}
@@ -1,12 +1,20 @@
 use std::marker::PhantomData;
 
-use macroquad::prelude::{is_key_down, KeyCode, Vec2};
+use macroquad::prelude::Vec2;
 
-use rand::{rngs::ThreadRng, Rng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use crate::entity::creature::Creature;
 use crate::entity::{Object, Physics};
 
+use self::{
+    input::{Action, Input},
+    replay::{InputRecorder, Recording, ReplayController},
+};
+
+pub mod input;
+pub mod replay;
+
 pub trait Controller<A> {
     /// Update the controller
     /// # Arguments
@@ -23,26 +31,48 @@ impl<A: Object> NoneController<A> {
     }
 }
 
+impl<A: Object> Default for NoneController<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<A: Object> Controller<A> for NoneController<A> {
     // Just use default trait implementations
 }
 
-/// A controller that controls objects using the keyboard
-pub struct KeyboardController;
+/// The state of the player's controls for a single update
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct PlayerInput {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    /// Drop through one-way platforms
+    pub down: bool,
+}
 
-impl KeyboardController {
-    pub fn new() -> KeyboardController {
-        KeyboardController
+impl PlayerInput {
+    /// Packs the input into a single byte
+    pub fn to_bits(self) -> u8 {
+        self.left as u8 | (self.right as u8) << 1 | (self.jump as u8) << 2 | (self.down as u8) << 3
     }
-}
 
-impl Controller<Creature> for KeyboardController {
-    fn update(&mut self, object: &mut Creature, _: f32) {
+    /// Unpacks input that was packed with `to_bits`
+    pub fn from_bits(bits: u8) -> Self {
+        Self { left: bits & 1 != 0, right: bits & 2 != 0, jump: bits & 4 != 0, down: bits & 8 != 0 }
+    }
+
+    /// Moves a creature according to the input
+    /// # Arguments
+    /// `object` - The creature to move
+    pub fn apply(self, object: &mut Creature) {
+        // Dead creatures can't be controlled
+        let input = if object.health.is_dead() { PlayerInput::default() } else { self };
         let move_accel = object.move_accel;
-        let x_accel = if is_key_down(KeyCode::Left) {
+        let x_accel = if input.left {
             -move_accel * if object.is_on_ground() { 1.0 } else { 0.6 }
         }
-        else if is_key_down(KeyCode::Right) {
+        else if input.right {
             move_accel * if object.is_on_ground() { 1.0 } else { 0.6 }
         }
         else {
@@ -51,24 +81,90 @@ impl Controller<Creature> for KeyboardController {
         let new_accel = Vec2::new(x_accel, object.acceleration().y);
         object.set_acceleration(new_accel);
 
-        let jump_accel = object.jump_accel;
-        if object.is_on_ground() && is_key_down(KeyCode::Up) {
-            let new_velocity = object.velocity() + Vec2::new(0.0, -jump_accel);
-            object.set_velocity(new_velocity);
+        object.drop_through = input.down;
+        object.set_jump_held(input.jump);
+    }
+}
+
+/// A controller that controls objects using the keyboard or a gamepad
+pub struct KeyboardController {
+    input: Input,
+}
+
+impl KeyboardController {
+    /// Create a new keyboard controller
+    /// # Arguments
+    /// `input` - The input to read the player's actions from
+    pub fn new(input: Input) -> KeyboardController {
+        KeyboardController { input }
+    }
+
+    /// Reads the current state of the keyboard and gamepads
+    pub fn read_input(&mut self) -> PlayerInput {
+        self.input.update();
+        PlayerInput {
+            left: self.input.is_down(Action::MoveLeft),
+            right: self.input.is_down(Action::MoveRight),
+            jump: self.input.is_down(Action::Jump),
+            down: self.input.is_down(Action::Down),
+        }
+    }
+}
+
+impl Controller<Creature> for KeyboardController {
+    fn update(&mut self, object: &mut Creature, _: f32) {
+        self.read_input().apply(object);
+    }
+}
+
+/// The controller used for the player, which can either be played live, recorded, or replayed from
+/// an earlier recording
+pub enum PlayerController {
+    Keyboard(KeyboardController),
+    Recorder(InputRecorder),
+    Replay(ReplayController),
+}
+
+impl PlayerController {
+    /// Gets the inputs recorded so far, if the player is being recorded
+    pub fn recording(&self) -> Option<&Recording> {
+        match self {
+            PlayerController::Recorder(recorder) => Some(recorder.recording()),
+            _ => None,
+        }
+    }
+
+    /// Checks if the player's input is being played back from a recording
+    pub fn is_replay(&self) -> bool {
+        matches!(self, PlayerController::Replay(_))
+    }
+}
+
+impl Controller<Creature> for PlayerController {
+    fn update(&mut self, object: &mut Creature, secs: f32) {
+        match self {
+            PlayerController::Keyboard(controller) => controller.update(object, secs),
+            PlayerController::Recorder(controller) => controller.update(object, secs),
+            PlayerController::Replay(controller) => controller.update(object, secs),
         }
     }
 }
 
 /// A controller that controls objects using randomness
 pub struct RandomController {
-    rng: ThreadRng,
+    rng: StdRng,
     move_time: f32,
     wait_time: f32,
 }
 
 impl RandomController {
-    pub fn new(move_time: f32) -> RandomController {
-        RandomController { rng: rand::thread_rng(), move_time, wait_time: 0.0 }
+    /// Create a new random controller
+    /// # Arguments
+    /// `move_time` - The time between changes of direction
+    /// `seed` - The seed for the random number generator, the same seed always produces the same
+    /// sequence of moves
+    pub fn new(move_time: f32, seed: u64) -> RandomController {
+        RandomController { rng: StdRng::seed_from_u64(seed), move_time, wait_time: 0.0 }
     }
 }
 
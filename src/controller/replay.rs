@@ -0,0 +1,193 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+
+use crate::{
+    controller::{Controller, KeyboardController, PlayerInput},
+    entity::creature::Creature,
+};
+
+/// The player's input for every update of a session, along with everything else needed to replay
+/// the session exactly
+pub struct Recording {
+    /// The number of updates per second the session was recorded at
+    pub tick_rate: u32,
+    /// The seed used for the random number generators in the session
+    pub seed: u64,
+    /// The player's input, one entry per update
+    pub inputs: Vec<PlayerInput>,
+}
+
+static VERSION: u8 = 1;
+static MAGIC_ID: [u8; 3] = *b"RPL";
+
+impl Recording {
+    pub fn new(tick_rate: u32, seed: u64) -> Self {
+        Self { tick_rate, seed, inputs: vec![] }
+    }
+
+    /// Loads a recording from a file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open: {}", path.display()))?;
+        let mut file = BufReader::new(file);
+
+        let mut header = [0; 20];
+        file.read_exact(&mut header).context("Could not read replay header")?;
+
+        // Check the magic id
+        if header[0..3] != MAGIC_ID {
+            anyhow::bail!("Invalid magic id");
+        }
+
+        // Check the version number
+        if header[3] != VERSION {
+            anyhow::bail!("Invalid replay version");
+        }
+
+        let tick_rate = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let seed = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let num_runs = u32::from_le_bytes(header[16..20].try_into().unwrap());
+
+        // Inputs are stored as runs of identical input, which keeps the file small as the input
+        // rarely changes between updates
+        let mut inputs = vec![];
+        for i in 0..num_runs {
+            let mut run = [0; 3];
+            file.read_exact(&mut run)
+                .with_context(|| format!("Could not read input run {i} of {num_runs}"))?;
+            let input = PlayerInput::from_bits(run[0]);
+            let length = u16::from_le_bytes(run[1..3].try_into().unwrap());
+            inputs.extend(std::iter::repeat_n(input, length as usize));
+        }
+
+        Ok(Self { tick_rate, seed, inputs })
+    }
+
+    /// Saves the recording to a file
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut runs: Vec<(PlayerInput, u16)> = vec![];
+        for &input in &self.inputs {
+            match runs.last_mut() {
+                Some((last, length)) if *last == input && *length < u16::MAX => *length += 1,
+                _ => runs.push((input, 1)),
+            }
+        }
+
+        let file =
+            File::create(path).with_context(|| format!("failed to create: {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+
+        file.write_all(&MAGIC_ID)?;
+        file.write_all(&[VERSION])?;
+        file.write_all(&self.tick_rate.to_le_bytes())?;
+        file.write_all(&self.seed.to_le_bytes())?;
+        file.write_all(&(runs.len() as u32).to_le_bytes())?;
+        for (input, length) in runs {
+            file.write_all(&[input.to_bits()])?;
+            file.write_all(&length.to_le_bytes())?;
+        }
+        file.flush().with_context(|| format!("failed to write: {}", path.display()))
+    }
+}
+
+/// A controller that records keyboard input while controlling the object with it
+pub struct InputRecorder {
+    keyboard: KeyboardController,
+    recording: Recording,
+}
+
+impl InputRecorder {
+    /// Create a new input recorder
+    /// # Arguments
+    /// `keyboard` - The controller to read the player's input with
+    /// `recording` - The recording to add the input to
+    pub fn new(keyboard: KeyboardController, recording: Recording) -> Self {
+        Self { keyboard, recording }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+}
+
+impl Controller<Creature> for InputRecorder {
+    fn update(&mut self, object: &mut Creature, _: f32) {
+        let input = self.keyboard.read_input();
+        self.recording.inputs.push(input);
+        input.apply(object);
+    }
+}
+
+/// A controller that plays back previously recorded input. Once the recording runs out the object
+/// is left with no input.
+pub struct ReplayController {
+    inputs: Vec<PlayerInput>,
+    tick: usize,
+}
+
+impl ReplayController {
+    pub fn new(recording: Recording) -> Self {
+        Self { inputs: recording.inputs, tick: 0 }
+    }
+
+    /// Gets the input for the next update, or no input once the recording has run out
+    pub fn next_input(&mut self) -> PlayerInput {
+        let input = self.inputs.get(self.tick).copied().unwrap_or_default();
+        self.tick += 1;
+        input
+    }
+}
+
+impl Controller<Creature> for ReplayController {
+    fn update(&mut self, object: &mut Creature, _: f32) {
+        self.next_input().apply(object);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(bits: u8) -> PlayerInput {
+        PlayerInput::from_bits(bits)
+    }
+
+    #[test]
+    fn recordings_round_trip() {
+        let mut recording = Recording::new(144, 0x0123_4567_89ab_cdef);
+        // A run longer than fits in a single run length, which has to be split in two
+        recording.inputs.extend(std::iter::repeat_n(input(0b0010), u16::MAX as usize + 10));
+        recording.inputs.extend([input(0b0101), input(0b1000), input(0b1000), input(0)]);
+
+        let path = std::env::temp_dir().join(format!("recording-{}.rpl", std::process::id()));
+        recording.save(&path).unwrap();
+        let file_size = std::fs::metadata(&path).unwrap().len();
+        let loaded = Recording::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        // A 20 byte header, followed by 3 bytes for each of the 5 runs
+        assert_eq!(file_size, 20 + 5 * 3);
+        assert_eq!(loaded.tick_rate, 144);
+        assert_eq!(loaded.seed, 0x0123_4567_89ab_cdef);
+        assert_eq!(loaded.inputs, recording.inputs);
+    }
+
+    #[test]
+    fn replays_recorded_input() {
+        let inputs: Vec<_> = (0..16).map(input).collect();
+        let mut recording = Recording::new(60, 1);
+        recording.inputs = inputs.clone();
+
+        let mut controller = ReplayController::new(recording);
+        let replayed: Vec<_> = (0..20).map(|_| controller.next_input()).collect();
+        assert_eq!(replayed[..16], inputs[..]);
+        // Once the recording runs out there is no input
+        assert_eq!(replayed[16..], [PlayerInput::default(); 4]);
+    }
+}
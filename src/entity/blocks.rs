@@ -1,24 +1,28 @@
-use macroquad::prelude::{Rect, Vec2};
+use macroquad::{
+    prelude::{Rect, Vec2},
+    texture::Texture2D,
+};
 
 use crate::{
-    entity::Object,
+    entity::{Draw, Object},
     map::Map,
     sprite::{Animation, AnimationPlayer},
 };
 
 pub struct DamageBlock {
     block_rect: Rect,
-    _damage: f32,
+    damage: f32,
     animation_player: AnimationPlayer,
 }
 
 impl DamageBlock {
     pub fn new(block_rect: Rect, damage: f32, animation: Animation) -> Self {
-        Self {
-            block_rect,
-            _damage: damage,
-            animation_player: AnimationPlayer::new(animation),
-        }
+        Self { block_rect, damage, animation_player: AnimationPlayer::new(animation) }
+    }
+
+    /// Gets the amount of damage done to creatures that touch the block
+    pub fn damage(&self) -> f32 {
+        self.damage
     }
 }
 
@@ -39,8 +43,41 @@ impl Object for DamageBlock {
     fn update(&mut self, _: &Map, secs: f32) {
         self.animation_player.update(secs);
     }
+}
+
+impl Draw for DamageBlock {
+    fn draw(&self, spritesheet: Texture2D, camera: Vec2) {
+        self.animation_player.draw(spritesheet, self.block_rect.point() - camera);
+    }
+}
+
+/// An invisible area that sets where the player respawns when they touch it
+pub struct Checkpoint {
+    area: Rect,
+}
+
+impl Checkpoint {
+    pub fn new(area: Rect) -> Self {
+        Self { area }
+    }
+}
+
+impl Object for Checkpoint {
+    fn position(&self) -> Vec2 {
+        self.area.point()
+    }
 
-    fn draw(&self, camera: Vec2) {
-        self.animation_player.draw(self.block_rect.point() - camera);
+    fn set_position(&mut self, new_pos: Vec2) {
+        self.area.move_to(new_pos);
     }
+
+    fn bounds(&self) -> Rect {
+        self.area
+    }
+
+    fn update(&mut self, _: &Map, _: f32) {}
+}
+
+impl Draw for Checkpoint {
+    fn draw(&self, _: Texture2D, _: Vec2) {}
 }
@@ -1,11 +1,21 @@
-use macroquad::prelude::{Rect, Vec2};
+use macroquad::{
+    prelude::{Rect, Vec2},
+    texture::Texture2D,
+};
 
 use crate::{
-    entity::{self, Object, PhysicalProperties, Physics, GRAVITY},
+    entity::{self, health::Health, Draw, Object, PhysicalProperties, Physics, Wall, GRAVITY},
     map::Map,
-    sprite::{Animation, AnimationPlayer},
+    sprite::{Animation, AnimationPlayer, AnimationSet},
 };
 
+/// The number of seconds a creature can't be hurt for after taking damage
+static INVULNERABLE_TIME: f32 = 1.0;
+/// The velocity a creature is knocked back with when it is hurt (m/s)
+static KNOCKBACK: Vec2 = Vec2::new(4.0, -4.0);
+/// How many times per second a creature flashes while it is invulnerable
+static FLASH_RATE: f32 = 10.0;
+
 pub struct Creature {
     pub accel: Vec2,
     pub vel: Vec2,
@@ -13,18 +23,89 @@ pub struct Creature {
     pub base_bounds: Rect,
     pub base_hitbox: Rect,
     pub on_ground: bool,
+    /// The side the creature is pushing against a wall on
+    pub wall: Option<Wall>,
+    /// Whether the creature falls through one-way platforms
+    pub drop_through: bool,
     pub properties: PhysicalProperties,
     pub move_accel: f32,
     pub jump_accel: f32,
+    pub jump: JumpProperties,
+    /// Whether the jump control is held
+    jump_held: bool,
+    /// Whether the creature is rising from a jump that can still be cut short
+    jumping: bool,
+    /// Whether the creature is rising from a jump off a wall
+    wall_jumping: bool,
+    /// The seconds left that the creature can still jump for after leaving the ground
+    coyote_timer: f32,
+    /// The seconds left that a jump press is remembered for before landing
+    buffer_timer: f32,
+    pub health: Health,
     pub animations: CreatureAnimations,
     pub animation_player: AnimationPlayer,
 }
 
+/// Tunes how a creature's jumps feel
+#[derive(Copy, Clone)]
+pub struct JumpProperties {
+    /// The seconds after walking off a ledge that the creature can still jump
+    pub coyote_time: f32,
+    /// The seconds before landing that a jump press is remembered for
+    pub buffer_time: f32,
+    /// The fraction of upwards velocity kept when the jump control is released early
+    pub release_cut: f32,
+    /// The fastest the creature falls while sliding down a wall (m/s)
+    pub wall_slide_speed: f32,
+    /// The velocity of a jump off a wall, with a positive x away from the wall (m/s)
+    pub wall_jump: Vec2,
+}
+
+impl Default for JumpProperties {
+    fn default() -> Self {
+        Self {
+            coyote_time: 0.1,
+            buffer_time: 0.1,
+            release_cut: 0.5,
+            wall_slide_speed: 1.5,
+            wall_jump: Vec2::new(4.0, -5.0),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct CreatureAnimations {
     pub idle: Animation,
     pub walk: Animation,
     pub jump: Animation,
     pub fall: Animation,
+    pub wall_slide: Animation,
+    pub wall_jump: Animation,
+}
+
+impl CreatureAnimations {
+    /// The names of the animations a creature can use
+    pub const NAMES: [&'static str; 6] =
+        ["idle", "walk", "jump", "fall", "wall_slide", "wall_jump"];
+
+    /// Gets a creature's animations from an animation set. `wall_slide` and `wall_jump` are
+    /// optional, and fall back to `fall` and `jump` for creatures that don't climb walls.
+    /// # Return
+    /// Returns the animations, or an error if the set is missing an animation or has one that
+    /// creatures don't use
+    pub fn from_set(set: &AnimationSet) -> anyhow::Result<Self> {
+        set.check_names(&Self::NAMES)?;
+        let jump = set.clip("jump")?;
+        let fall = set.clip("fall")?;
+        Ok(Self {
+            idle: set.clip("idle")?,
+            walk: set.clip("walk")?,
+            wall_slide: set.optional_clip("wall_slide").unwrap_or_else(|| fall.clone()),
+            wall_jump: set.optional_clip("wall_jump").unwrap_or_else(|| jump.clone()),
+            jump,
+            fall,
+        })
+    }
 }
 
 impl Physics for Creature {
@@ -46,6 +127,20 @@ impl Physics for Creature {
     fn set_on_ground(&mut self, value: bool) {
         self.on_ground = value
     }
+    fn set_hit_wall(&mut self, wall: Option<Wall>) {
+        self.wall = wall;
+    }
+    fn is_dropping_through(&self) -> bool {
+        self.drop_through
+    }
+    fn max_fall_speed(&self) -> f32 {
+        if self.is_wall_sliding() {
+            self.jump.wall_slide_speed
+        }
+        else {
+            f32::INFINITY
+        }
+    }
     fn get_properties(&self) -> PhysicalProperties {
         self.properties
     }
@@ -64,7 +159,13 @@ impl Object for Creature {
         self.base_bounds.offset(self.pos)
     }
 
+    fn hitbox(&self) -> Rect {
+        self.base_hitbox.offset(self.pos)
+    }
+
     fn update(&mut self, map: &Map, secs: f32) {
+        self.health.update(secs);
+        self.update_jump(secs);
         entity::physics(self, map, secs);
 
         if self.acceleration().x.abs() != 0.0 {
@@ -87,25 +188,36 @@ impl Object for Creature {
                 }
             }
         }
-        // The entity is in the air, so it must be jumping or falling
+        // The entity is in the air, so it must be jumping, falling or sliding down a wall
+        else if self.is_wall_sliding() {
+            self.animation_player.play(self.animations.wall_slide.clone());
+        }
+        else if self.velocity().y > 0.0 {
+            self.animation_player.play(self.animations.fall.clone());
+        }
+        else if self.wall_jumping {
+            self.animation_player.play(self.animations.wall_jump.clone());
+        }
         else {
-            if self.velocity().y > 0.0 {
-                self.animation_player.play(self.animations.fall.clone());
-            }
-            else {
-                self.animation_player.play(self.animations.jump.clone());
-            }
+            self.animation_player.play(self.animations.jump.clone());
         }
 
         self.animation_player.update(secs);
     }
+}
 
-    fn draw(&self, camera: Vec2) {
-        self.animation_player.draw(self.pos - camera);
+impl Draw for Creature {
+    fn draw(&self, spritesheet: Texture2D, camera: Vec2) {
+        // Flash while invulnerable
+        let flash = (self.health.invulnerable_time() * FLASH_RATE) as i32;
+        if flash % 2 == 0 {
+            self.animation_player.draw(spritesheet, self.pos - camera);
+        }
     }
 }
 
 impl Creature {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         position: Vec2,
         base_bounds: Rect,
@@ -113,6 +225,7 @@ impl Creature {
         properties: PhysicalProperties,
         move_accel: f32,
         jump_accel: f32,
+        max_health: f32,
         animations: CreatureAnimations,
     ) -> Self {
         Self {
@@ -122,9 +235,18 @@ impl Creature {
             base_bounds,
             base_hitbox,
             on_ground: false,
+            wall: None,
+            drop_through: false,
             properties,
             move_accel,
             jump_accel,
+            jump: JumpProperties::default(),
+            jump_held: false,
+            jumping: false,
+            wall_jumping: false,
+            coyote_timer: 0.0,
+            buffer_timer: 0.0,
+            health: Health::new(max_health),
             animation_player: AnimationPlayer::new(animations.idle.clone()),
             animations,
         }
@@ -133,4 +255,236 @@ impl Creature {
     pub fn center(&self) -> Vec2 {
         self.bounds().center()
     }
+
+    /// Sets whether the jump control is held. Pressing it starts a jump as soon as the creature
+    /// can, and releasing it early cuts the jump short.
+    pub fn set_jump_held(&mut self, held: bool) {
+        if held && !self.jump_held {
+            self.buffer_timer = self.jump.buffer_time;
+        }
+        self.jump_held = held;
+    }
+
+    /// Checks if the creature is falling while pushing against a wall
+    pub fn is_wall_sliding(&self) -> bool {
+        !self.on_ground && self.wall.is_some() && self.vel.y >= 0.0
+    }
+
+    /// Starts a buffered jump if the creature is on the ground, only just left it, or is against
+    /// a wall, and cuts the current jump short if the jump control was released
+    /// # Arguments
+    /// `secs` - The total seconds elapsed since the last update
+    fn update_jump(&mut self, secs: f32) {
+        if self.on_ground {
+            self.coyote_timer = self.jump.coyote_time;
+        }
+        if self.buffer_timer > 0.0 && self.coyote_timer > 0.0 {
+            self.vel.y = -self.jump_accel;
+            self.on_ground = false;
+            self.jumping = true;
+            self.buffer_timer = 0.0;
+            self.coyote_timer = 0.0;
+        }
+        else if let Some(wall) = self.wall.filter(|_| self.buffer_timer > 0.0 && !self.on_ground)
+        {
+            // Kick away from the wall
+            let away = match wall {
+                Wall::Left => 1.0,
+                Wall::Right => -1.0,
+            };
+            self.vel = Vec2::new(self.jump.wall_jump.x * away, self.jump.wall_jump.y);
+            self.wall = None;
+            self.jumping = true;
+            self.wall_jumping = true;
+            self.buffer_timer = 0.0;
+        }
+        else if self.jumping && !self.jump_held && self.vel.y < 0.0 {
+            self.vel.y *= self.jump.release_cut;
+            self.jumping = false;
+        }
+        if self.vel.y >= 0.0 {
+            self.jumping = false;
+            self.wall_jumping = false;
+        }
+
+        self.coyote_timer = (self.coyote_timer - secs).max(0.0);
+        self.buffer_timer = (self.buffer_timer - secs).max(0.0);
+    }
+
+    /// Damages the creature and knocks it back, unless it is dead or invulnerable
+    /// # Arguments
+    /// `amount` - The amount of health to lose
+    /// `source` - The position of whatever hurt the creature, which it is knocked away from
+    /// # Return
+    /// Returns true if the creature was hurt
+    pub fn hurt(&mut self, amount: f32, source: Vec2) -> bool {
+        if !self.health.damage(amount, INVULNERABLE_TIME) {
+            return false;
+        }
+        let direction = if self.center().x < source.x { -1.0 } else { 1.0 };
+        self.vel = Vec2::new(KNOCKBACK.x * direction, KNOCKBACK.y);
+        self.on_ground = false;
+        // Being knocked off the ground isn't the same as walking off a ledge
+        self.coyote_timer = 0.0;
+        self.jumping = false;
+        self.wall_jumping = false;
+        true
+    }
+
+    /// Brings the creature back to life at full health
+    pub fn respawn(&mut self) {
+        self.health.restore();
+        self.vel = Vec2::ZERO;
+        self.on_ground = false;
+        self.wall = None;
+        self.jumping = false;
+        self.wall_jumping = false;
+        self.coyote_timer = 0.0;
+        self.buffer_timer = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::PIXEL_SCALE, fixtures};
+
+    static TICK: f32 = 1.0 / 60.0;
+
+    fn creature(position: Vec2) -> Creature {
+        fixtures::creature(position, Vec2::splat(32.0))
+    }
+
+    /// Creates a creature standing on the floor
+    fn standing(map: &Map) -> Creature {
+        let mut creature = creature(Vec2::new(32.0, 14.0 * 32.0));
+        for _ in 0..10 {
+            creature.update(map, TICK);
+        }
+        assert!(creature.on_ground);
+        creature
+    }
+
+    /// Updates the creature with the jump control held for a number of ticks
+    /// # Return
+    /// Returns the highest the creature got (the lowest y)
+    fn hold_jump(creature: &mut Creature, map: &Map, held: bool, ticks: u32) -> f32 {
+        let mut top = creature.pos.y;
+        for _ in 0..ticks {
+            creature.set_jump_held(held);
+            creature.update(map, TICK);
+            top = top.min(creature.pos.y);
+        }
+        top
+    }
+
+    #[test]
+    fn holding_jump_only_jumps_once() {
+        let map = fixtures::floor_map(4, 16);
+        let mut creature = standing(&map);
+
+        let mut jumps = 0;
+        for _ in 0..300 {
+            let was_on_ground = creature.on_ground;
+            creature.set_jump_held(true);
+            creature.update(&map, TICK);
+            if was_on_ground && !creature.on_ground {
+                jumps += 1;
+            }
+        }
+        assert_eq!(jumps, 1);
+        assert!(creature.on_ground);
+    }
+
+    #[test]
+    fn releasing_jump_early_cuts_it_short() {
+        let map = fixtures::floor_map(4, 16);
+        let mut held = standing(&map);
+        let start = held.pos.y;
+        let full = hold_jump(&mut held, &map, true, 60);
+
+        let mut tapped = standing(&map);
+        hold_jump(&mut tapped, &map, true, 3);
+        let cut = hold_jump(&mut tapped, &map, false, 60);
+
+        assert!(full < cut, "full jump: {full}, cut jump: {cut}");
+        assert!(cut < start);
+    }
+
+    #[test]
+    fn can_jump_just_after_leaving_the_ground() {
+        let map = fixtures::map(4, 16, |_, _| false);
+
+        // The creature just walked off a ledge
+        let mut late = creature(Vec2::new(32.0, 32.0));
+        late.on_ground = true;
+        hold_jump(&mut late, &map, false, 3);
+        assert!(late.vel.y > 0.0);
+        hold_jump(&mut late, &map, true, 1);
+        assert!(late.vel.y < 0.0);
+
+        let mut too_late = creature(Vec2::new(32.0, 32.0));
+        too_late.on_ground = true;
+        hold_jump(&mut too_late, &map, false, 12);
+        hold_jump(&mut too_late, &map, true, 1);
+        assert!(too_late.vel.y > 0.0);
+    }
+
+    #[test]
+    fn jump_pressed_before_landing_is_buffered() {
+        let map = fixtures::floor_map(4, 16);
+        let floor = standing(&map).pos.y;
+
+        // Press jump just before landing
+        let mut early = creature(Vec2::new(32.0, 11.0 * 32.0));
+        while early.pos.y < floor - 8.0 {
+            hold_jump(&mut early, &map, false, 1);
+        }
+        assert!(!early.on_ground);
+        let jumped = (0..6).any(|_| {
+            hold_jump(&mut early, &map, true, 1);
+            early.vel.y < 0.0
+        });
+        assert!(jumped, "the buffered jump never happened");
+
+        // Presses long before landing are forgotten
+        let mut too_early = creature(Vec2::new(32.0, 0.0));
+        hold_jump(&mut too_early, &map, true, 1);
+        hold_jump(&mut too_early, &map, false, 200);
+        assert!(too_early.on_ground);
+        assert_eq!(too_early.vel.y, 0.0);
+    }
+
+    #[test]
+    fn slides_down_and_jumps_off_walls() {
+        // A wall along the right side of the map
+        let map = fixtures::map(4, 16, |x, _| x == 3);
+
+        // Run into the wall while falling faster than a wall slide
+        let mut creature = creature(Vec2::new(0.0, 0.0));
+        creature.accel.x = creature.move_accel;
+        let max_step = creature.jump.wall_slide_speed * TICK * PIXEL_SCALE;
+        let mut ticks_on_wall = 0;
+        for _ in 0..60 {
+            let (start, vel) = (creature.pos.y, creature.vel.y);
+            hold_jump(&mut creature, &map, false, 1);
+            if creature.wall.is_some() {
+                if ticks_on_wall == 0 {
+                    assert!(vel > creature.jump.wall_slide_speed, "{vel}");
+                }
+                ticks_on_wall += 1;
+                // Sliding starts on the tick the wall is touched
+                let step = creature.pos.y - start;
+                assert!(step <= max_step + 0.001, "step: {step}, max step: {max_step}");
+            }
+        }
+        assert!(ticks_on_wall > 10, "{ticks_on_wall}");
+        assert_eq!(creature.wall, Some(Wall::Right));
+        assert!(creature.is_wall_sliding());
+
+        hold_jump(&mut creature, &map, true, 1);
+        assert!(creature.vel.x < 0.0, "{}", creature.vel);
+        assert!(creature.vel.y < 0.0, "{}", creature.vel);
+        assert_eq!(creature.wall, None);
+    }
 }
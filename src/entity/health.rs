@@ -0,0 +1,99 @@
+/// The health of a creature. After taking damage the creature is invulnerable for a short time, so
+/// that it isn't hurt again on every update while it is touching something dangerous.
+pub struct Health {
+    max: f32,
+    current: f32,
+    // The seconds left before the creature can be hurt again
+    invulnerable_time: f32,
+}
+
+impl Health {
+    /// Creates a health component at full health
+    /// # Arguments
+    /// `max` - The maximum health
+    pub fn new(max: f32) -> Self {
+        Self { max, current: max, invulnerable_time: 0.0 }
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_time > 0.0
+    }
+
+    /// Gets the number of seconds before the creature can be hurt again
+    pub fn invulnerable_time(&self) -> f32 {
+        self.invulnerable_time
+    }
+
+    /// Takes damage, unless the creature is dead or invulnerable
+    /// # Arguments
+    /// `amount` - The amount of health to lose
+    /// `invulnerable_time` - The number of seconds before the creature can be hurt again
+    /// # Return
+    /// Returns true if the damage was taken
+    pub fn damage(&mut self, amount: f32, invulnerable_time: f32) -> bool {
+        if self.is_dead() || self.is_invulnerable() {
+            return false;
+        }
+        self.current = (self.current - amount).max(0.0);
+        self.invulnerable_time = invulnerable_time;
+        true
+    }
+
+    /// Restores the creature to full health
+    pub fn restore(&mut self) {
+        self.current = self.max;
+        self.invulnerable_time = 0.0;
+    }
+
+    /// Counts down the time the creature is invulnerable for
+    /// # Arguments
+    /// `secs` - The total seconds elapsed since the last update
+    pub fn update(&mut self, secs: f32) {
+        self.invulnerable_time = (self.invulnerable_time - secs).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invulnerable_after_damage() {
+        let mut health = Health::new(3.0);
+        assert!(health.damage(1.0, 1.0));
+        assert!(!health.damage(1.0, 1.0));
+        assert_eq!(health.current(), 2.0);
+
+        health.update(0.5);
+        assert!(health.is_invulnerable());
+        health.update(0.5);
+        assert!(!health.is_invulnerable());
+        assert!(health.damage(1.0, 1.0));
+        assert_eq!(health.current(), 1.0);
+    }
+
+    #[test]
+    fn dead_creatures_take_no_damage() {
+        let mut health = Health::new(1.0);
+        assert!(health.damage(5.0, 0.0));
+        assert!(health.is_dead());
+        assert_eq!(health.current(), 0.0);
+        assert!(!health.damage(1.0, 0.0));
+
+        health.restore();
+        assert!(!health.is_dead());
+        assert_eq!(health.current(), 1.0);
+    }
+}
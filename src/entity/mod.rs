@@ -1,9 +1,14 @@
-use macroquad::prelude::{Rect, Vec2};
+use macroquad::{
+    prelude::{Rect, Vec2},
+    texture::Texture2D,
+};
 
-use crate::{controller::Controller, map::Map};
+use crate::{collision, controller::Controller, map::Map, tiles::TileCollision};
 
 pub mod blocks;
 pub mod creature;
+pub mod health;
+pub mod registry;
 
 static PIXEL_SCALE: f32 = 52.0;
 static GRAVITY: f32 = 9.8;
@@ -13,22 +18,55 @@ static GRAVITY: f32 = 9.8;
 pub struct Entity<A, B> {
     pub object: A,
     pub controller: B,
+    // The position of the object before the most recent update, used for interpolation
+    previous_position: Vec2,
 }
 
 impl<A: Object, B: Controller<A>> Entity<A, B> {
+    pub fn new(object: A, controller: B) -> Self {
+        let previous_position = object.position();
+        Self { object, controller, previous_position }
+    }
+
     pub fn update(&mut self, map: &Map, secs: f32) {
+        self.previous_position = self.object.position();
         // Update the controller
         self.controller.update(&mut self.object, secs);
         // Update the object
         self.object.update(map, secs);
     }
 
-    pub fn draw(&self, camera: Vec2) {
-        self.object.draw(camera);
+    /// Moves the object without interpolating from its old position
+    /// # Arguments
+    /// `position` - The position to move to
+    pub fn teleport(&mut self, position: Vec2) {
+        self.object.set_position(position);
+        self.previous_position = position;
+    }
+
+    /// Gets the object's position between the last two updates
+    /// # Arguments
+    /// `alpha` - How far between the previous and current update to interpolate (0.0 to 1.0)
+    pub fn interpolated_position(&self, alpha: f32) -> Vec2 {
+        self.previous_position.lerp(self.object.position(), alpha)
     }
 }
 
-/// Objects are things that can be drawn to the screen
+impl<A: Draw, B: Controller<A>> Entity<A, B> {
+    /// Draws the entity at its interpolated position
+    /// # Arguments
+    /// `spritesheet` - The texture containing the object's sprites
+    /// `camera` - The offset due to the camera position
+    /// `alpha` - How far between the previous and current update to interpolate (0.0 to 1.0)
+    pub fn draw(&self, spritesheet: Texture2D, camera: Vec2, alpha: f32) {
+        // Objects draw themselves at their current position, so shift the camera to compensate
+        let offset = self.object.position() - self.interpolated_position(alpha);
+        self.object.draw(spritesheet, camera + offset);
+    }
+}
+
+/// Objects are the things that exist in the game world. Objects only hold simulation state, so
+/// they can be created and updated without a graphics context.
 pub trait Object {
     /// Gets the object's position
     /// # Return
@@ -45,16 +83,29 @@ pub trait Object {
     /// Returns a rectangle representing the objects physical bounds
     fn bounds(&self) -> Rect;
 
+    /// Gets the area where the object touches other objects
+    /// # Return
+    /// Returns a rectangle representing the object's hitbox
+    fn hitbox(&self) -> Rect {
+        // If no implementation is provided, then the object's physical bounds are used
+        self.bounds()
+    }
+
     /// Updates the object
     /// # Arguments
     /// `map` - The map where the object is currently
     /// `secs` - The total seconds elapsed since the last update
     fn update(&mut self, map: &Map, secs: f32);
+}
 
+/// Objects that can be drawn to the screen. Drawing is a separate pass over the object's state,
+/// with the texture supplied by the caller.
+pub trait Draw: Object {
     /// Draws the object on the screen
     /// # Arguments
+    /// `spritesheet` - The texture containing the object's sprites
     /// `camera` - The offset due to the camera position
-    fn draw(&self, camera: Vec2);
+    fn draw(&self, spritesheet: Texture2D, camera: Vec2);
 }
 
 /// Defines objects that physics can be applied on. Note that default implementations are provided
@@ -100,17 +151,40 @@ pub trait Physics: Object {
 
     /// Sets that object is hitting a wall or not
     /// # Arguments
-    /// `value` - true => object is hitting a wall, false => not hitting a wall
-    fn set_hit_wall(&mut self, _value: bool) {
+    /// `wall` - The side of the object that is hitting a wall, or `None` if it isn't hitting one
+    fn set_hit_wall(&mut self, _wall: Option<Wall>) {
         // Do nothing if an implementation is not provided
     }
 
+    /// Checks if the object is dropping through one-way platforms
+    /// # Return
+    /// Returns true if the object should fall through one-way tiles instead of landing on them
+    fn is_dropping_through(&self) -> bool {
+        // If no implementation is provided, then the object always lands on one-way tiles
+        false
+    }
+
+    /// Gets the fastest the object can currently fall
+    /// # Return
+    /// Returns the highest downwards velocity the object can move at (m/s)
+    fn max_fall_speed(&self) -> f32 {
+        // If no implementation is provided, then the object falls as fast as gravity takes it
+        f32::INFINITY
+    }
+
     /// Gets the objects physical properties
     /// # Return
     /// Returns the object's physical properties
     fn get_properties(&self) -> PhysicalProperties;
 }
 
+/// The side of an object that is touching a wall
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Wall {
+    Left,
+    Right,
+}
+
 #[derive(Copy, Clone)]
 /// A structure that contains important values that are used for physics calculations
 pub struct PhysicalProperties {
@@ -154,14 +228,16 @@ fn clamp(value: f32, min: f32, max: f32) -> f32 {
 /// `map` - the map where the object is
 /// `secs` - the number of seconds since the last update
 pub fn physics<T: Physics>(object: &mut T, map: &Map, dt: f32) {
+    let was_on_ground = object.is_on_ground();
     let mut new_velocity = object.velocity();
     // Increase velocity due to acceleration
-    new_velocity = new_velocity + object.acceleration() * dt;
+    new_velocity += object.acceleration() * dt;
     // Decrease velocity due to air resistance
-    new_velocity = new_velocity + air_resistance(object) * dt;
-    // Decrease X velocity due to friction
-    if object.is_on_ground() && apply_friction(object) {
-        let friction = 0.9 * GRAVITY * object.get_properties().stop_bonus * dt;
+    new_velocity += air_resistance(object) * dt;
+    // Decrease X velocity due to friction from the ground the object is standing on
+    if was_on_ground && apply_friction(object) {
+        let ground_friction = map.ground_friction(object.bounds());
+        let friction = 0.9 * GRAVITY * object.get_properties().stop_bonus * ground_friction * dt;
         new_velocity.x = if new_velocity.x < 0.0 {
             (new_velocity.x + friction).min(0.0)
         }
@@ -178,25 +254,53 @@ pub fn physics<T: Physics>(object: &mut T, map: &Map, dt: f32) {
     let mut new_position = object.position();
     // Calculate the new x position
     let move_x = object.velocity().x * dt * PIXEL_SCALE;
-    let collision_x = map_collision_x(object, map, move_x);
-    if move_x.abs() > collision_x.abs() {
-        new_position.x += collision_x;
+    // An object standing on a slope has its feet inside the tiles next to the slope, so the bottom
+    // of the object is ignored when checking for walls. Slopes are no steeper than 45°, so the
+    // object rises by at most the distance it moves.
+    let bounds = object.bounds();
+    let on_slope = was_on_ground
+        && matches!(
+            map.ground_surface(bounds.center().x, bounds.bottom()),
+            Some((_, TileCollision::Slope(_)))
+        );
+    let foot_height = if on_slope { bounds.w / 2.0 + move_x.abs() } else { 0.0 };
+    let wall_bounds = Rect { h: (bounds.h - foot_height).max(0.0), ..bounds };
+    let hit = collision::sweep(map, wall_bounds, Vec2::new(move_x, 0.0), |info, _| {
+        // One-way and sloped tiles never block horizontal movement
+        info.collision == TileCollision::Solid
+    });
+    if let Some(hit) = hit {
+        new_position.x += move_x * hit.time;
         new_velocity.x = 0.0;
-        object.set_hit_wall(true);
+        object.set_hit_wall(Some(if move_x < 0.0 { Wall::Left } else { Wall::Right }));
     }
     else {
         new_position.x += move_x;
-        object.set_hit_wall(false);
+        object.set_hit_wall(None);
     }
     object.set_position(new_position);
 
-    // Calculate the new y position
+    // Calculate the new y position. Touching a wall can limit how fast the object falls, so this
+    // is checked after moving along x.
+    new_velocity.y = new_velocity.y.min(object.max_fall_speed());
     let move_y = new_velocity.y * dt * PIXEL_SCALE;
-    let collision_y = map_collision_y(object, map, move_y);
-    if move_y.abs() > collision_y.abs() {
-        new_position.y += collision_y;
+    let dropping_through = object.is_dropping_through();
+    let hit = collision::sweep(map, object.bounds(), Vec2::new(0.0, move_y), |info, normal| {
+        match info.collision {
+            TileCollision::None => false,
+            TileCollision::Solid => true,
+            // One-way tiles only block objects falling onto them from above. Tiles the object is
+            // already inside are ignored, so objects can jump up through them.
+            TileCollision::OneWay => normal.y < 0.0 && !dropping_through,
+            // Objects only stand on slopes at their center, which is handled after moving. The
+            // bottom of a slope is solid.
+            TileCollision::Slope(_) => normal.y > 0.0,
+        }
+    });
+    if let Some(hit) = hit {
+        new_position.y += move_y * hit.time;
         new_velocity.y = 0.0;
-        object.set_on_ground(true);
+        object.set_on_ground(hit.normal.y < 0.0);
     }
     else {
         new_position.y += move_y;
@@ -204,6 +308,21 @@ pub fn physics<T: Physics>(object: &mut T, map: &Map, dt: f32) {
     }
     object.set_position(new_position);
 
+    // Stand on the slope (or the ground at the end of it) under the center of the object. Objects
+    // that were already on the ground are pulled down onto the ground below them, so they stay
+    // attached when running downhill.
+    if new_velocity.y >= 0.0 {
+        let bounds = object.bounds();
+        if let Some((surface, _)) = map.ground_surface(bounds.center().x, bounds.bottom()) {
+            if bounds.bottom() >= surface || was_on_ground {
+                new_position.y += surface - bounds.bottom();
+                new_velocity.y = 0.0;
+                object.set_on_ground(true);
+                object.set_position(new_position);
+            }
+        }
+    }
+
     object.set_velocity(new_velocity);
 }
 
@@ -230,119 +349,290 @@ fn air_resistance<T: Physics>(object: &T) -> Vec2 {
     }
 }
 
-/// Calculates the maximum distance the object can travel in the x direction
-fn map_collision_x<T: Physics>(object: &T, map: &Map, max_dist: f32) -> f32 {
-    if max_dist != 0.0 {
-        let tile_size = map.tile_size() as f32;
-        // Map bounds
-        let map_top = 0;
-        let map_bottom = (map.height - 1) as u32;
-        let map_left = 0;
-        let map_right = (map.width - 1) as u32;
-        // Calculate range of x values to check
-        let (start_x, end_x, dir_x) = if object.velocity().x < 0.0 {
-            ((object.bounds().left() / tile_size).floor() as u32, map_left, -1)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entity::blocks::DamageBlock,
+        fixtures,
+        tiles::{Slope, TileSet},
+    };
+
+    static EMPTY: u16 = 0;
+    static ICE: u16 = 1;
+    static GROUND: u16 = 2;
+    static MUD: u16 = 3;
+    static PLATFORM: u16 = 4;
+    static UP_45: u16 = 5;
+    static DOWN_45: u16 = 6;
+    static UP_22_LOW: u16 = 7;
+    static UP_22_HIGH: u16 = 8;
+    static DOWN_22_HIGH: u16 = 9;
+    static DOWN_22_LOW: u16 = 10;
+
+    /// A box that is either pushed along, or moves with no input
+    struct Slider {
+        pos: Vec2,
+        vel: Vec2,
+        on_ground: bool,
+        dropping: bool,
+        push: f32,
+    }
+
+    impl Slider {
+        fn new(pos: Vec2, vel: Vec2) -> Self {
+            Self { pos, vel, on_ground: false, dropping: false, push: 0.0 }
         }
-        else {
-            ((object.bounds().right() / tile_size).ceil() as u32, map_right, 1)
-        };
-        // Calculate the range of y values to check
-        let start_y = std::cmp::max(map_top, (object.bounds().top() / tile_size).floor() as u32);
-        let end_y = std::cmp::min(map_bottom, (object.bounds().bottom() / tile_size).ceil() as u32);
-        // Check the tiles for collision
-        let tile = scan_tiles_x(map, start_x, end_x, dir_x, start_y, end_y) as f32;
-        // Calculate the new maximum distance
-        if object.velocity().x < 0.0 {
-            (tile + 1.0) * tile_size - object.bounds().left()
+    }
+
+    impl Object for Slider {
+        fn position(&self) -> Vec2 {
+            self.pos
         }
-        else {
-            tile * tile_size - object.bounds().right()
+
+        fn set_position(&mut self, new_pos: Vec2) {
+            self.pos = new_pos;
         }
-    }
-    else {
-        // The object will not move at all
-        0.0
-    }
-}
 
-/// Scan for solid tiles in the x direction
-fn scan_tiles_x(map: &Map, start_x: u32, end_x: u32, dir_x: i32, start_y: u32, end_y: u32) -> u32 {
-    if dir_x > 0 {
-        for x in start_x..=end_x {
-            for y in start_y..end_y {
-                if map.tile_info_at(x as usize, y as usize).solid {
-                    return x;
-                }
-            }
+        fn bounds(&self) -> Rect {
+            Rect::new(self.pos.x, self.pos.y, 32.0, 32.0)
+        }
+
+        fn update(&mut self, map: &Map, secs: f32) {
+            physics(self, map, secs);
         }
     }
-    else {
-        for x in (start_x..=end_x).rev() {
-            for y in start_y..end_y {
-                if map.tile_info_at(x as usize, y as usize).solid {
-                    return x;
-                }
+
+    impl Physics for Slider {
+        fn acceleration(&self) -> Vec2 {
+            Vec2::new(self.push, GRAVITY)
+        }
+
+        fn velocity(&self) -> Vec2 {
+            self.vel
+        }
+
+        fn set_velocity(&mut self, new_vel: Vec2) {
+            self.vel = new_vel;
+        }
+
+        fn is_on_ground(&self) -> bool {
+            self.on_ground
+        }
+
+        fn set_on_ground(&mut self, value: bool) {
+            self.on_ground = value;
+        }
+
+        fn is_dropping_through(&self) -> bool {
+            self.dropping
+        }
+
+        fn get_properties(&self) -> PhysicalProperties {
+            PhysicalProperties {
+                c_drag: 0.0,
+                mass: 1.0,
+                cross_area: 1.0,
+                max_vel_x: 10.0,
+                stop_bonus: 1.0,
             }
         }
     }
 
-    end_x
-}
+    fn tileset() -> TileSet {
+        fixtures::tileset_with(&[
+            (TileCollision::None, 0.0),
+            (TileCollision::Solid, 0.25),
+            (TileCollision::Solid, 1.0),
+            (TileCollision::Solid, 2.0),
+            (TileCollision::OneWay, 1.0),
+            (TileCollision::Slope(Slope::Up45), 1.0),
+            (TileCollision::Slope(Slope::Down45), 1.0),
+            (TileCollision::Slope(Slope::Up22Low), 1.0),
+            (TileCollision::Slope(Slope::Up22High), 1.0),
+            (TileCollision::Slope(Slope::Down22High), 1.0),
+            (TileCollision::Slope(Slope::Down22Low), 1.0),
+        ])
+    }
+
+    /// Creates a map with a floor along the bottom row, made of the given tiles
+    fn floor_map(floor: &[u16]) -> Map {
+        let mut tiles = vec![EMPTY; floor.len() * 3];
+        tiles.extend_from_slice(floor);
+        Map::new(floor.len(), 4, tiles, tileset())
+    }
+
+    /// Creates a map with a one-way platform along row 4 and a solid floor along row 7
+    fn platform_map() -> Map {
+        let mut tiles = vec![EMPTY; 4 * 8];
+        tiles[16..20].fill(PLATFORM);
+        tiles[28..32].fill(GROUND);
+        Map::new(4, 8, tiles, tileset())
+    }
+
+    /// Creates a map with a solid floor along row 5, and the given tiles along row 4
+    fn hill_map(hill: &[u16]) -> Map {
+        let mut tiles = vec![EMPTY; hill.len() * 4];
+        tiles.extend_from_slice(hill);
+        tiles.extend(std::iter::repeat_n(GROUND, hill.len()));
+        Map::new(hill.len(), 6, tiles, tileset())
+    }
 
-/// Calculates the maximum distance the object can travel in the x direction
-fn map_collision_y<T: Physics>(object: &T, map: &Map, max_dist: f32) -> f32 {
-    if max_dist != 0.0 {
-        let tile_size = map.tile_size() as f32;
-        // Map bounds
-        let map_top = 0;
-        let map_bottom = (map.height - 1) as u32;
-        let map_left = 0;
-        let map_right = (map.width - 1) as u32;
-        // Calculate range of y values to check
-        let (start_y, end_y, dir_y) = if object.velocity().y < 0.0 {
-            ((object.bounds().top() / tile_size).floor() as u32, map_top, -1)
+    /// Pushes an object to the right across a hill, checking that it stays on the ground and
+    /// moves smoothly
+    /// # Return
+    /// Returns the height of the object once it has crossed the hill
+    fn walk_over(map: &Map, start: Vec2) -> f32 {
+        let mut slider = Slider { on_ground: true, push: 5.0, ..Slider::new(start, Vec2::ZERO) };
+        while slider.pos.x < 128.0 {
+            let previous = slider.pos;
+            slider.update(map, 1.0 / 60.0);
+            assert!(slider.on_ground, "left the ground at {}", slider.pos);
+            // The object never moves vertically more than it moves horizontally
+            let step = slider.pos - previous;
+            assert!(step.x > 0.0, "stopped at {}", slider.pos);
+            assert!(step.y.abs() <= step.x + 0.01, "moved by {step} at {}", slider.pos);
         }
-        else {
-            ((object.bounds().bottom() / tile_size).ceil() as u32, map_bottom, 1)
-        };
-        // Calculate the range of y values to check
-        let start_x = std::cmp::max(map_left, (object.bounds().left() / tile_size).floor() as u32);
-        let end_x = std::cmp::min(map_right, (object.bounds().right() / tile_size).ceil() as u32);
-        // Check the tiles for collision
-        let tile = scan_tiles_y(map, start_y, end_y, dir_y, start_x, end_x) as f32;
-        // Calculate the new maximum distance
-        if object.velocity().y < 0.0 {
-            (tile + 1.0) * tile_size - object.bounds().top()
+        slider.pos.y
+    }
+
+    fn assert_near(position: Vec2, expected: Vec2) {
+        assert!(position.abs_diff_eq(expected, 0.01), "expected {expected}, found {position}");
+    }
+
+    /// Moves an object for a second
+    fn run(slider: &mut Slider, map: &Map) {
+        for _ in 0..60 {
+            slider.update(map, 1.0 / 60.0);
         }
-        else {
-            tile * tile_size - object.bounds().bottom()
+    }
+
+    /// Slides an object along a floor until it stops
+    /// # Return
+    /// Returns the distance travelled in pixels
+    fn stopping_distance(floor_tile: u16) -> f32 {
+        let map = floor_map(&[floor_tile; 30]);
+        let start = Vec2::new(32.0, 64.0);
+        let mut slider = Slider { on_ground: true, ..Slider::new(start, Vec2::new(5.0, 0.0)) };
+        for _ in 0..10_000 {
+            slider.update(&map, 1.0 / 60.0);
+            if slider.vel.x == 0.0 {
+                return slider.pos.x - start.x;
+            }
         }
+        panic!("Object did not stop");
     }
-    else {
-        // The object will not move at all
-        0.0
+
+    #[test]
+    fn stopping_distance_scales_with_friction() {
+        let ice = stopping_distance(ICE);
+        let ground = stopping_distance(GROUND);
+        let mud = stopping_distance(MUD);
+        assert!(ice > ground && ground > mud, "ice: {ice}, ground: {ground}, mud: {mud}");
+
+        // Stopping distance is inversely proportional to friction
+        assert!((ice / ground - 4.0).abs() < 0.2, "ice: {ice}, ground: {ground}");
+        assert!((ground / mud - 2.0).abs() < 0.1, "ground: {ground}, mud: {mud}");
     }
-}
 
-/// Scan for solid tiles in the y direction
-fn scan_tiles_y(map: &Map, start_y: u32, end_y: u32, dir_y: i32, start_x: u32, end_x: u32) -> u32 {
-    if dir_y > 0 {
-        for y in start_y..=end_y {
-            for x in start_x..end_x {
-                if map.tile_info_at(x as usize, y as usize).solid {
-                    return y;
-                }
-            }
+    #[test]
+    fn ground_friction_averages_solid_tiles_under_bounds() {
+        let map = floor_map(&[ICE, MUD, EMPTY, GROUND]);
+        assert_eq!(map.ground_friction(Rect::new(0.0, 64.0, 32.0, 32.0)), 0.25);
+        assert_eq!(map.ground_friction(Rect::new(16.0, 64.0, 32.0, 32.0)), 1.125);
+        // Empty tiles are ignored
+        assert_eq!(map.ground_friction(Rect::new(48.0, 64.0, 32.0, 32.0)), 2.0);
+        // Nothing solid below, or outside of the map
+        assert_eq!(map.ground_friction(Rect::new(64.0, 32.0, 32.0, 32.0)), 1.0);
+        assert_eq!(map.ground_friction(Rect::new(0.0, 96.0, 32.0, 32.0)), 1.0);
+    }
+
+    #[test]
+    fn lands_on_one_way_platform_from_above() {
+        let map = platform_map();
+        let mut slider = Slider::new(Vec2::ZERO, Vec2::ZERO);
+        run(&mut slider, &map);
+        assert_eq!(slider.pos.y, 96.0);
+        assert!(slider.on_ground);
+    }
+
+    #[test]
+    fn jumps_up_through_one_way_platform() {
+        let map = platform_map();
+        let mut slider = Slider::new(Vec2::new(0.0, 192.0), Vec2::new(0.0, -7.0));
+        let mut highest = slider.pos.y;
+        for _ in 0..120 {
+            slider.update(&map, 1.0 / 60.0);
+            highest = highest.min(slider.pos.y);
         }
+        // The object passed above the platform, then landed on top of it
+        assert!(highest + 32.0 < 128.0, "highest: {highest}");
+        assert_eq!(slider.pos.y, 96.0);
     }
-    else {
-        for y in (start_y..=end_y).rev() {
-            for x in start_x..end_x {
-                if map.tile_info_at(x as usize, y as usize).solid {
-                    return y;
-                }
-            }
+
+    #[test]
+    fn moves_sideways_through_one_way_platform() {
+        let map = platform_map();
+        let mut slider = Slider::new(Vec2::new(0.0, 128.0), Vec2::new(5.0, 0.0));
+        slider.update(&map, 1.0 / 60.0);
+        assert!(slider.pos.x > 0.0);
+    }
+
+    #[test]
+    fn drops_through_one_way_platform() {
+        let map = platform_map();
+        let mut slider = Slider::new(Vec2::new(0.0, 96.0), Vec2::ZERO);
+        run(&mut slider, &map);
+        assert_eq!(slider.pos.y, 96.0);
+
+        slider.dropping = true;
+        run(&mut slider, &map);
+        assert_eq!(slider.pos.y, 192.0);
+    }
+
+    #[test]
+    fn walks_up_slopes() {
+        let map = hill_map(&[EMPTY, EMPTY, UP_45, GROUND, GROUND, GROUND]);
+        assert_eq!(walk_over(&map, Vec2::new(0.0, 128.0)).round(), 96.0);
+
+        let map = hill_map(&[EMPTY, EMPTY, UP_22_LOW, UP_22_HIGH, GROUND, GROUND]);
+        assert_eq!(walk_over(&map, Vec2::new(0.0, 128.0)).round(), 96.0);
+    }
+
+    #[test]
+    fn runs_down_slopes_without_leaving_the_ground() {
+        let map = hill_map(&[GROUND, DOWN_45, EMPTY, EMPTY, EMPTY, EMPTY]);
+        assert_eq!(walk_over(&map, Vec2::new(0.0, 96.0)).round(), 128.0);
+
+        let map = hill_map(&[GROUND, DOWN_22_HIGH, DOWN_22_LOW, EMPTY, EMPTY, EMPTY]);
+        assert_eq!(walk_over(&map, Vec2::new(0.0, 96.0)).round(), 128.0);
+    }
+
+    #[test]
+    fn lands_on_slope() {
+        let map = hill_map(&[EMPTY, UP_45, GROUND]);
+        // The center of the object is a quarter of the way across the slope
+        let mut slider = Slider::new(Vec2::new(24.0, 0.0), Vec2::ZERO);
+        run(&mut slider, &map);
+        assert_near(slider.pos, Vec2::new(24.0, 160.0 - 8.0 - 32.0));
+        assert!(slider.on_ground);
+    }
+
+    #[test]
+    fn objects_update_without_textures() {
+        // Tests have no graphics context, so this fails if anything here needs a texture
+        let map = fixtures::floor_map(4, 8);
+        let mut creature = fixtures::creature(Vec2::new(32.0, 0.0), Vec2::new(32.0, 32.0));
+        let lava_rect = Rect::new(0.0, 6.0 * 32.0, 32.0, 32.0);
+        let mut lava = DamageBlock::new(lava_rect, 1.0, fixtures::animation());
+
+        for _ in 0..120 {
+            creature.update(&map, 1.0 / 60.0);
+            lava.update(&map, 1.0 / 60.0);
         }
+        // The creature fell onto the floor along the bottom row
+        assert!(creature.on_ground);
+        assert_eq!(creature.bounds().bottom(), 7.0 * 32.0);
+        assert_eq!(lava.position(), lava_rect.point());
     }
-    end_y
 }
@@ -0,0 +1,284 @@
+use std::any::Any;
+
+use macroquad::{
+    prelude::{Rect, Vec2},
+    texture::Texture2D,
+};
+
+use crate::{
+    controller::Controller,
+    entity::{Draw, Entity},
+    map::Map,
+};
+
+/// A stable identifier for an entity. Ids are never reused, so an id for an entity that has been
+/// despawned won't refer to a different entity later.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct EntityId(u64);
+
+/// The behavior shared by every entity, which lets entities with different objects and
+/// controllers be stored together
+pub trait EntityBehavior<Kind> {
+    /// Updates the entity
+    /// # Arguments
+    /// `map` - The map where the entity is
+    /// `secs` - The total seconds elapsed since the last update
+    /// `commands` - Used to spawn and despawn entities once the update is finished
+    fn update(&mut self, map: &Map, secs: f32, commands: &mut Commands<Kind>);
+
+    /// Gets the area where the entity touches other entities
+    fn hitbox(&self) -> Rect;
+
+    /// Draws the entity at its interpolated position
+    /// # Arguments
+    /// `spritesheet` - The texture containing the entity's sprites
+    /// `camera` - The offset due to the camera position
+    /// `alpha` - How far between the previous and current update to interpolate (0.0 to 1.0)
+    fn draw(&self, spritesheet: Texture2D, camera: Vec2, alpha: f32);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<Kind, A: Draw + 'static, B: Controller<A> + 'static> EntityBehavior<Kind> for Entity<A, B> {
+    fn update(&mut self, map: &Map, secs: f32, _: &mut Commands<Kind>) {
+        Entity::update(self, map, secs);
+    }
+
+    fn hitbox(&self) -> Rect {
+        self.object.hitbox()
+    }
+
+    fn draw(&self, spritesheet: Texture2D, camera: Vec2, alpha: f32) {
+        Entity::draw(self, spritesheet, camera, alpha);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// An entity in the registry
+struct Slot<Kind> {
+    id: EntityId,
+    kind: Kind,
+    entity: Box<dyn EntityBehavior<Kind>>,
+}
+
+/// Changes to the registry requested while it is being updated. These are applied after every
+/// entity has been updated.
+pub struct Commands<Kind> {
+    next_id: u64,
+    spawned: Vec<Slot<Kind>>,
+    despawned: Vec<EntityId>,
+}
+
+impl<Kind> Commands<Kind> {
+    /// Spawns an entity once the update is finished
+    /// # Arguments
+    /// `kind` - The kind of entity
+    /// `entity` - The entity to spawn
+    /// # Return
+    /// Returns the id the entity will have
+    pub fn spawn(&mut self, kind: Kind, entity: impl EntityBehavior<Kind> + 'static) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.spawned.push(Slot { id, kind, entity: Box::new(entity) });
+        id
+    }
+
+    /// Despawns an entity once the update is finished
+    pub fn despawn(&mut self, id: EntityId) {
+        self.despawned.push(id);
+    }
+}
+
+/// Stores every entity in the game, along with a kind used to tell them apart. Entities are
+/// updated and drawn in the order they were spawned.
+pub struct EntityRegistry<Kind> {
+    next_id: u64,
+    // Sorted by id, as ids are given out in increasing order
+    slots: Vec<Slot<Kind>>,
+}
+
+impl<Kind: Copy + 'static> EntityRegistry<Kind> {
+    pub fn new() -> Self {
+        Self { next_id: 0, slots: vec![] }
+    }
+
+    /// Adds an entity to the registry
+    /// # Return
+    /// Returns the id of the new entity
+    pub fn spawn(&mut self, kind: Kind, entity: impl EntityBehavior<Kind> + 'static) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.slots.push(Slot { id, kind, entity: Box::new(entity) });
+        id
+    }
+
+    /// Removes an entity from the registry
+    /// # Return
+    /// Returns true if the entity was in the registry
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        match self.index(id) {
+            Some(index) => {
+                self.slots.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Updates every entity, then spawns and despawns the entities requested during the update
+    /// # Arguments
+    /// `map` - The map where the entities are
+    /// `secs` - The total seconds elapsed since the last update
+    pub fn update(&mut self, map: &Map, secs: f32) {
+        let mut commands = Commands { next_id: self.next_id, spawned: vec![], despawned: vec![] };
+        for slot in &mut self.slots {
+            slot.entity.update(map, secs, &mut commands);
+        }
+
+        // Spawn first so that entities can be despawned in the same update they are spawned
+        self.next_id = commands.next_id;
+        self.slots.extend(commands.spawned);
+        for id in commands.despawned {
+            self.despawn(id);
+        }
+    }
+
+    fn index(&self, id: EntityId) -> Option<usize> {
+        self.slots.binary_search_by_key(&id, |slot| slot.id).ok()
+    }
+
+    /// Gets the kind of an entity
+    pub fn kind(&self, id: EntityId) -> Option<Kind> {
+        self.index(id).map(|index| self.slots[index].kind)
+    }
+
+    /// Gets an entity as its concrete type
+    /// # Return
+    /// Returns the entity, or `None` if it has been despawned or is a different type
+    pub fn get<T: 'static>(&self, id: EntityId) -> Option<&T> {
+        self.index(id).and_then(|index| self.slots[index].entity.as_any().downcast_ref())
+    }
+
+    /// Gets an entity as its concrete type
+    /// # Return
+    /// Returns the entity, or `None` if it has been despawned or is a different type
+    pub fn get_mut<T: 'static>(&mut self, id: EntityId) -> Option<&mut T> {
+        self.index(id).and_then(|index| self.slots[index].entity.as_any_mut().downcast_mut())
+    }
+
+    /// Iterates over every entity in the order they were spawned
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, Kind, &dyn EntityBehavior<Kind>)> {
+        self.slots.iter().map(|slot| (slot.id, slot.kind, slot.entity.as_ref()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+impl<Kind: Copy + 'static> Default for EntityRegistry<Kind> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::TileSet;
+
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    enum Kind {
+        Spawner,
+        Spawned,
+    }
+
+    /// An entity that spawns a new entity on its first update, and despawns itself on its second.
+    /// Spawned entities start after their second update, so they do nothing.
+    struct Spawner {
+        id: Option<EntityId>,
+        updates: u32,
+    }
+
+    impl EntityBehavior<Kind> for Spawner {
+        fn update(&mut self, _: &Map, _: f32, commands: &mut Commands<Kind>) {
+            self.updates += 1;
+            match self.updates {
+                1 => {
+                    commands.spawn(Kind::Spawned, Spawner { id: None, updates: 2 });
+                }
+                2 => commands.despawn(self.id.unwrap()),
+                _ => {}
+            }
+        }
+
+        fn hitbox(&self) -> Rect {
+            Rect::default()
+        }
+
+        fn draw(&self, _: Texture2D, _: Vec2, _: f32) {}
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn map() -> Map {
+        Map::new(1, 1, vec![0], TileSet::empty())
+    }
+
+    #[test]
+    fn spawns_and_despawns_during_update() {
+        let map = map();
+        let mut registry = EntityRegistry::new();
+        let a = registry.spawn(Kind::Spawner, Spawner { id: None, updates: 0 });
+        registry.get_mut::<Spawner>(a).unwrap().id = Some(a);
+
+        // The spawned entity isn't updated until the next update
+        registry.update(&map, 1.0);
+        let ids: Vec<_> = registry.iter().map(|(id, kind, _)| (id, kind)).collect();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[0], (a, Kind::Spawner));
+        let b = ids[1].0;
+        assert_eq!(ids[1].1, Kind::Spawned);
+        assert_eq!(registry.get::<Spawner>(b).unwrap().updates, 2);
+
+        registry.update(&map, 1.0);
+        assert_eq!(registry.len(), 1);
+        assert!(registry.get::<Spawner>(a).is_none());
+        assert_eq!(registry.kind(b), Some(Kind::Spawned));
+        assert_eq!(registry.get::<Spawner>(b).unwrap().updates, 3);
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let mut registry = EntityRegistry::new();
+        let a = registry.spawn(Kind::Spawner, Spawner { id: None, updates: 0 });
+        assert!(registry.despawn(a));
+        assert!(!registry.despawn(a));
+
+        let b = registry.spawn(Kind::Spawner, Spawner { id: None, updates: 0 });
+        assert_ne!(a, b);
+        assert_eq!(registry.kind(a), None);
+        assert_eq!(registry.kind(b), Some(Kind::Spawner));
+        // Entities can only be fetched as their own type
+        assert!(registry.get::<u32>(b).is_none());
+    }
+}
@@ -0,0 +1,85 @@
+use macroquad::prelude::{Rect, UVec2, Vec2};
+
+use crate::{
+    entity::{
+        creature::{Creature, CreatureAnimations},
+        PhysicalProperties,
+    },
+    map::Map,
+    sprite::{Animation, Sprite},
+    tiles::{TileCollision, TileInfo, TileSet},
+};
+
+/// Creates a tileset with no texture
+/// # Arguments
+/// `tiles` - The collision and friction of each tile
+pub(crate) fn tileset_with(tiles: &[(TileCollision, f32)]) -> TileSet {
+    let tile_info =
+        tiles.iter().map(|&(collision, friction)| TileInfo { collision, friction }).collect();
+    TileSet { tile_info, ..TileSet::empty() }
+}
+
+/// Creates a tileset where tile 0 is empty and tile 1 is solid
+pub(crate) fn tileset() -> TileSet {
+    tileset_with(&[(TileCollision::None, 1.0), (TileCollision::Solid, 1.0)])
+}
+
+/// Creates a map using `tileset()`
+/// # Arguments
+/// `solid` - Whether the tile at a column and row is solid
+pub(crate) fn map(width: usize, height: usize, solid: impl Fn(usize, usize) -> bool) -> Map {
+    let tiles = (0..width * height).map(|i| solid(i % width, i / width) as u16).collect();
+    Map::new(width, height, tiles, tileset())
+}
+
+/// Creates a map with a solid floor along the bottom row
+pub(crate) fn floor_map(width: usize, height: usize) -> Map {
+    map(width, height, |_, y| y == height - 1)
+}
+
+/// Creates an animation with a single 32x32 frame
+pub(crate) fn animation() -> Animation {
+    Animation {
+        id: 0,
+        sprite: Sprite {
+            offset: UVec2::ZERO,
+            frame_width: 32,
+            frame_height: 32,
+            num_frames_x: 1,
+            num_frames_y: 1,
+        },
+        frame_times: vec![0.0],
+        repeat: true,
+    }
+}
+
+/// Creates a creature that moves like the player
+/// # Arguments
+/// `position` - The top left corner of the creature
+/// `size` - The size of the creature's bounds and hitbox
+pub(crate) fn creature(position: Vec2, size: Vec2) -> Creature {
+    let bounds = Rect::new(0.0, 0.0, size.x, size.y);
+    Creature::new(
+        position,
+        bounds,
+        bounds,
+        PhysicalProperties {
+            c_drag: 0.470,
+            mass: 70.00,
+            cross_area: 0.760,
+            max_vel_x: 9.000,
+            stop_bonus: 6.000,
+        },
+        6.0,
+        5.0,
+        1.0,
+        CreatureAnimations {
+            idle: animation(),
+            walk: animation(),
+            jump: animation(),
+            fall: animation(),
+            wall_slide: animation(),
+            wall_jump: animation(),
+        },
+    )
+}
@@ -1,205 +1,382 @@
+use std::path::Path;
+
 use anyhow::Context;
 use macroquad::{
-    prelude::{Rect, UVec2, Vec2, WHITE},
-    texture::{draw_texture, load_texture, Texture2D},
-    window::{screen_height, screen_width},
+    prelude::{Rect, Vec2, BLACK},
+    texture::{load_texture, Texture2D},
+    window::clear_background,
 };
 
 use crate::{
     bitfont::BitFont,
-    controller::{KeyboardController, NoneController, RandomController},
+    camera::Camera,
+    collision::{ContactEvent, Contacts},
+    controller::{replay::Recording, NoneController, PlayerController, RandomController},
     entity::{
-        blocks::DamageBlock,
+        blocks::{Checkpoint, DamageBlock},
         creature::{Creature, CreatureAnimations},
-        Entity, PhysicalProperties,
+        registry::{EntityId, EntityRegistry},
+        Entity, Object, PhysicalProperties, Physics,
     },
     map::Map,
-    sprite::{Animation, Sprite, NEXT_ANIMATION_ID},
-    tiles::{TileInfo, TileSet},
+    sprite::{Animation, AnimationSet},
+    tiled,
 };
 
 pub struct Game {
     map: Map,
-    player: Entity<Creature, KeyboardController>,
-    cat: Entity<Creature, RandomController>,
-    lava: Vec<Entity<DamageBlock, NoneController<DamageBlock>>>,
+    entities: EntityRegistry<EntityKind>,
+    player: EntityId,
+    /// Where the player respawns after dying
+    respawn_position: Vec2,
+    /// The seconds since the player died
+    time_dead: f32,
+    /// The number of times the player can respawn before the game is over
+    lives: u32,
+    /// The seed for the random number generators in the game
+    seed: u64,
+    contacts: Contacts<EntityId>,
+    /// The contacts between entities that began or ended during the last update
+    contact_events: Vec<ContactEvent<EntityId>>,
     font: BitFont,
-    camera: Vec2,
-    background: Texture2D,
+    camera: Camera,
+    animations: Animations,
+    textures: Textures,
+}
+
+/// The kinds of entity in the game
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EntityKind {
+    Player,
+    Cat,
+    Lava,
+    Checkpoint,
+}
+
+type PlayerEntity = Entity<Creature, PlayerController>;
+type CatEntity = Entity<Creature, RandomController>;
+type LavaEntity = Entity<DamageBlock, NoneController<DamageBlock>>;
+type CheckpointEntity = Entity<Checkpoint, NoneController<Checkpoint>>;
+
+/// The number of seconds before the player respawns after dying
+static RESPAWN_DELAY: f32 = 1.5;
+
+/// How far the view shakes when the player is hurt (pixels)
+static HURT_SHAKE: f32 = 6.0;
+/// The number of seconds the view shakes for when the player is hurt
+static HURT_SHAKE_TIME: f32 = 0.3;
+
+/// The number of times the player can respawn in a new game
+static PLAYER_LIVES: u32 = 3;
+
+/// The animations of each kind of entity, loaded from animation set files
+struct Animations {
+    player: CreatureAnimations,
+    cat: CreatureAnimations,
+    lava: Animation,
+}
+
+/// The textures used for drawing the game. These are kept apart from the simulation state, which
+/// can be updated without a graphics context.
+struct Textures {
+    tileset: Texture2D,
+    player: Texture2D,
+    cat: Texture2D,
+    lava: Texture2D,
+    /// The image of each of the map's parallax layers
+    parallax: Vec<Texture2D>,
 }
 
 impl Game {
-    pub async fn new() -> anyhow::Result<Self> {
-        #[rustfmt::skip]
-        let tile_info = vec![
-            TileInfo { solid: false, friction: 0.0 },
-
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-            TileInfo { solid: true, friction: 1.0 },
-        ];
-        let tileset = TileSet {
-            tile_size: 32,
-            sprite: load_texture("./assets/tileset.png").await.context("failed to load tileset")?,
-            tile_info,
+    /// Creates a new game
+    /// # Arguments
+    /// `map_path` - The map to play, either a binary map or a Tiled map
+    /// `player_controller` - The controller for the player
+    /// `seed` - The seed for the random number generators in the game
+    /// `font` - The font for the HUD
+    /// `view_size` - The size of the screen the game is drawn to
+    pub async fn new(
+        map_path: &Path,
+        player_controller: PlayerController,
+        seed: u64,
+        font: BitFont,
+        view_size: Vec2,
+    ) -> anyhow::Result<Self> {
+        let map = if tiled::is_tiled_map(map_path) {
+            tiled::load_map(map_path)?
+        }
+        else {
+            Map::load_map(map_path, None).context("Failed to load map")?
+        };
+
+        let player_set = AnimationSet::load(Path::new("./assets/creatures/player.json"))?;
+        let cat_set = AnimationSet::load(Path::new("./assets/creatures/cat.json"))?;
+        let lava_set = AnimationSet::load(Path::new("./assets/blocks/lava.json"))?;
+        lava_set.check_names(&["lava"])?;
+        let animations = Animations {
+            player: CreatureAnimations::from_set(&player_set)?,
+            cat: CreatureAnimations::from_set(&cat_set)?,
+            lava: lava_set.clip("lava")?,
+        };
+
+        let spawn = map.spawn.unwrap_or(Vec2::new(50.0, 50.0));
+        let mut entities = EntityRegistry::new();
+        let player = entities.spawn(
+            EntityKind::Player,
+            create_player(spawn, player_controller, animations.player.clone()),
+        );
+
+        spawn_objects(&mut entities, &map, seed, &animations)?;
+
+        let map_dir = map_path.parent().unwrap_or(Path::new(""));
+        let mut parallax = vec![];
+        for layer in &map.parallax {
+            let path = map_dir.join(&layer.texture);
+            parallax.push(
+                load_texture(&path.to_string_lossy()).await.with_context(|| {
+                    format!("Failed to load parallax image: {}", path.display())
+                })?,
+            );
+        }
+
+        let textures = Textures {
+            tileset: load_texture(&map.tileset().texture.to_string_lossy())
+                .await
+                .context("failed to load tileset")?,
+            player: load_sheet(&player_set).await?,
+            cat: load_sheet(&cat_set).await?,
+            lava: load_sheet(&lava_set).await?,
+            parallax,
         };
-        let map =
-            Map::load_map("./assets/maps/map1".as_ref(), tileset).context("Failed to load map")?;
-
-        let human_spritesheet = load_texture("./assets/creatures/player.png")
-            .await
-            .context("Failed to load human sprite")?;
-        let cat_spritesheet = load_texture("./assets/creatures/cat.png")
-            .await
-            .context("Failed to load cat sprite")?;
-        let player = create_player(Vec2::new(50.0, 50.0), human_spritesheet);
-        let cat = create_cat(Vec2::new(400.0, 50.0), cat_spritesheet);
-
-        let lava_texture = load_texture("./assets/blocks/lava_anim.png")
-            .await
-            .context("Failed to load lava sprite")?;
-        let lava = vec![
-            create_lava_block(Vec2::new(100.0, 800.0), lava_texture),
-            create_lava_block(Vec2::new(100.0 + 32.0, 800.0), lava_texture),
-            create_lava_block(Vec2::new(100.0 + 64.0, 800.0), lava_texture),
-        ];
-
-        let background = load_texture("./assets/background.png")
-            .await
-            .context("Failed to load background image")?;
-        let font_spritesheet =
-            load_texture("./assets/fonts/Victoria.png").await.context("Failed to load font")?;
-        let font = BitFont::new(32, 96, 8, 9, font_spritesheet);
-
-        Ok(Self { map, player, cat, lava, font, camera: Vec2::ZERO, background })
+        map.tileset().check_texture_size(
+            textures.tileset.width() as u32,
+            textures.tileset.height() as u32,
+        )?;
+
+        let player_center = player_ref(&entities, player).object.center();
+        let camera = Camera::new(view_size, map.size(), player_center);
+
+        Ok(Self {
+            map,
+            entities,
+            player,
+            respawn_position: spawn,
+            time_dead: 0.0,
+            lives: PLAYER_LIVES,
+            seed,
+            contacts: Contacts::new(64.0),
+            contact_events: vec![],
+            font,
+            camera,
+            animations,
+            textures,
+        })
     }
 
     pub fn update(&mut self, secs: f32) {
-        let map = &self.map;
-        self.player.update(map, secs);
-        self.cat.update(map, secs);
-        self.lava[0].update(map, secs);
-        self.lava[1].update(map, secs);
-        self.lava[2].update(map, secs);
+        self.map.update(secs);
+        self.entities.update(&self.map, secs);
+
+        let hitboxes: Vec<_> =
+            self.entities.iter().map(|(id, _, entity)| (id, entity.hitbox())).collect();
+        self.contact_events = self.contacts.update(&hitboxes);
+
+        let entities = &mut self.entities;
+        let player_id = self.player;
+        for id in self.contacts.touching(player_id) {
+            match entities.kind(id) {
+                Some(EntityKind::Lava) => {
+                    let lava = &entities.get::<LavaEntity>(id).unwrap().object;
+                    let (damage, source) = (lava.damage(), lava.bounds().center());
+                    if player_mut(entities, player_id).object.hurt(damage, source) {
+                        self.camera.shake(HURT_SHAKE, HURT_SHAKE_TIME);
+                    }
+                }
+                Some(EntityKind::Checkpoint)
+                    if !player_mut(entities, player_id).object.health.is_dead() =>
+                {
+                    let checkpoint = entities.get::<CheckpointEntity>(id).unwrap();
+                    self.respawn_position = checkpoint.object.position();
+                }
+                _ => {}
+            }
+        }
+
+        let player = player_mut(entities, player_id);
+        if player.object.health.is_dead() && self.lives > 0 {
+            self.time_dead += secs;
+            if self.time_dead >= RESPAWN_DELAY {
+                self.time_dead = 0.0;
+                self.lives -= 1;
+                player.teleport(self.respawn_position);
+                player.object.respawn();
+                self.camera.snap_to(player.object.center());
+            }
+        }
+
+        let player = &player.object;
+        self.camera.follow(player.center(), player.velocity(), secs);
     }
 
-    pub fn draw(&mut self) {
-        draw_texture(self.background, 0.0, 0.0, WHITE);
+    /// Checks if the player has died with no lives left
+    pub fn is_over(&self) -> bool {
+        self.lives == 0 && self.player().object.health.is_dead()
+    }
+
+    /// Starts the game again from the beginning of the map, keeping the player's controller
+    pub fn reset(&mut self) {
+        let spawn = self.map.spawn.unwrap_or(Vec2::new(50.0, 50.0));
+        let others: Vec<_> = self
+            .entities
+            .iter()
+            .filter(|&(_, kind, _)| kind != EntityKind::Player)
+            .map(|(id, _, _)| id)
+            .collect();
+        for id in others {
+            self.entities.despawn(id);
+        }
+        spawn_objects(&mut self.entities, &self.map, self.seed, &self.animations)
+            .expect("objects are checked when the game is created");
+
+        let player = player_mut(&mut self.entities, self.player);
+        player.teleport(spawn);
+        player.object.respawn();
+        self.camera.snap_to(player.object.center());
+        self.respawn_position = spawn;
+        self.time_dead = 0.0;
+        self.lives = PLAYER_LIVES;
+        self.contacts = Contacts::new(64.0);
+        self.contact_events.clear();
+    }
+
+    fn player(&self) -> &PlayerEntity {
+        player_ref(&self.entities, self.player)
+    }
+
+    /// Gets the contacts between entities that began or ended during the last update
+    pub fn contact_events(&self) -> &[ContactEvent<EntityId>] {
+        &self.contact_events
+    }
+
+    /// Gets the player's recorded input, if the player is being recorded
+    pub fn recording(&self) -> Option<&Recording> {
+        self.player().controller.recording()
+    }
+
+    /// Checks if the player's input is being played back from a recording
+    pub fn is_replay(&self) -> bool {
+        self.player().controller.is_replay()
+    }
 
-        // Center the camera on the player:
-        let width = screen_width();
-        let height = screen_height();
+    /// Draws the game
+    /// # Arguments
+    /// `view_size` - The size of the screen being drawn to
+    /// `alpha` - How far the simulation is between the previous and next update (0.0 to 1.0)
+    pub fn draw(&mut self, view_size: Vec2, alpha: f32) {
+        // Maps without parallax layers, or with layers that don't cover the screen, show black
+        clear_background(BLACK);
+        self.camera.set_view_size(view_size);
+        let viewport = self.camera.viewport(alpha);
+        let camera = viewport.point();
+        self.map.draw_parallax(&self.textures.parallax, viewport);
+        self.map.draw_background(self.textures.tileset, viewport);
+        for (_, kind, entity) in self.entities.iter() {
+            let texture = match kind {
+                EntityKind::Player => self.textures.player,
+                EntityKind::Cat => self.textures.cat,
+                EntityKind::Lava => self.textures.lava,
+                EntityKind::Checkpoint => continue,
+            };
+            entity.draw(texture, camera, alpha);
+        }
+        self.map.draw_foreground(self.textures.tileset, viewport);
 
-        self.camera = self.player.object.center() - Vec2::new(width / 2.0, height / 2.0);
-        self.camera.clamp(Vec2::ZERO, self.map.size());
+        let health = &self.player().object.health;
+        let hud = format!("Health: {}/{}  Lives: {}", health.current(), health.max(), self.lives);
+        self.font.draw_text(Vec2::ZERO, &hud);
+    }
+}
 
-        let camera = self.camera.round();
-        self.map.draw(camera);
-        self.player.draw(camera);
-        self.cat.draw(camera);
-        self.lava[0].draw(camera);
-        self.lava[1].draw(camera);
-        self.lava[2].draw(camera);
+/// Loads the spritesheet of an animation set, and checks that its animations fit inside it
+async fn load_sheet(set: &AnimationSet) -> anyhow::Result<Texture2D> {
+    let texture = load_texture(&set.sheet.to_string_lossy())
+        .await
+        .with_context(|| format!("Failed to load spritesheet: {}", set.sheet.display()))?;
+    set.check_texture_size(texture.width() as u32, texture.height() as u32)?;
+    Ok(texture)
+}
 
-        static TEST_STRING: &'static str = r#"Test string,
-with multiple lines."#;
-        self.font.draw_text(Vec2::ZERO, TEST_STRING);
+/// Spawns the entities for the objects placed in a map
+/// # Arguments
+/// `entities` - The registry to spawn the entities in
+/// `map` - The map containing the objects
+/// `seed` - The seed for the random number generators of the entities
+/// `animations` - The animations of each kind of entity
+fn spawn_objects(
+    entities: &mut EntityRegistry<EntityKind>,
+    map: &Map,
+    seed: u64,
+    animations: &Animations,
+) -> anyhow::Result<()> {
+    let mut num_cats = 0;
+    for object in &map.objects {
+        match object.kind.as_str() {
+            "cat" => {
+                let move_time = object.property("move_time", 0.5)?;
+                // Give each cat its own sequence of moves
+                let seed = seed.wrapping_add(num_cats);
+                num_cats += 1;
+                entities.spawn(
+                    EntityKind::Cat,
+                    create_cat(object.position, move_time, seed, animations.cat.clone()),
+                );
+            }
+            "lava" => {
+                let damage = object.property("damage", 1.0)?;
+                entities.spawn(
+                    EntityKind::Lava,
+                    create_lava_block(object.position, damage, animations.lava.clone()),
+                );
+            }
+            "checkpoint" => {
+                let size =
+                    Vec2::new(object.property("width", 32.0)?, object.property("height", 32.0)?);
+                let area = Rect::new(object.position.x, object.position.y, size.x, size.y);
+                entities.spawn(
+                    EntityKind::Checkpoint,
+                    CheckpointEntity::new(Checkpoint::new(area), NoneController::new()),
+                );
+            }
+            kind => anyhow::bail!(
+                "Unknown object type '{kind}' at ({}, {})",
+                object.position.x,
+                object.position.y
+            ),
+        }
     }
+    Ok(())
+}
+
+/// Gets the player from the registry
+fn player_ref(entities: &EntityRegistry<EntityKind>, id: EntityId) -> &PlayerEntity {
+    entities.get(id).expect("the player is never despawned")
+}
+
+/// Gets the player from the registry
+fn player_mut(entities: &mut EntityRegistry<EntityKind>, id: EntityId) -> &mut PlayerEntity {
+    entities.get_mut(id).expect("the player is never despawned")
 }
 
-fn create_player(position: Vec2, spritesheet: Texture2D) -> Entity<Creature, KeyboardController> {
-    let fw = 64;
-    let fh = 128;
-    let idle = Animation {
-        id: NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        sprite: Sprite {
-            spritesheet: spritesheet.clone(),
-            offset: UVec2::ZERO,
-            frame_width: fw,
-            frame_height: fh,
-            num_frames_x: 1,
-            num_frames_y: 1,
-        },
-        repeat: true,
-        frame_time: 0.0,
-    };
-    let walk = Animation {
-        id: NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        sprite: Sprite {
-            spritesheet: spritesheet.clone(),
-            offset: UVec2::new(1 * fw, 0),
-            frame_width: fw,
-            frame_height: fh,
-            num_frames_x: 6,
-            num_frames_y: 1,
-        },
-        repeat: true,
-        frame_time: 0.7,
-    };
-    let jump = Animation {
-        id: NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        sprite: Sprite {
-            spritesheet: spritesheet.clone(),
-            offset: UVec2::new(7 * fw, 1 * fh),
-            frame_width: fw,
-            frame_height: fh,
-            num_frames_x: 1,
-            num_frames_y: 1,
-        },
-        repeat: true,
-        frame_time: 0.6,
-    };
-    let fall = Animation {
-        id: NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        sprite: Sprite {
-            spritesheet: spritesheet.clone(),
-            offset: UVec2::new(8 * fw, 1 * fh),
-            frame_width: fw,
-            frame_height: fh,
-            num_frames_x: 2,
-            num_frames_y: 1,
-        },
-        repeat: true,
-        frame_time: 0.5,
-    };
-
-    Entity {
-        object: Creature::new(
+fn create_player(
+    position: Vec2,
+    controller: PlayerController,
+    animations: CreatureAnimations,
+) -> PlayerEntity {
+    Entity::new(
+        Creature::new(
             position,
             Rect::new(14.0, 36.0, 32.0, 92.0),
-            Rect::new(0.0, 0.0, 32.0, 32.0),
+            Rect::new(14.0, 36.0, 32.0, 92.0),
             PhysicalProperties {
                 c_drag: 0.470,
                 mass: 70.00,       // (kg)
@@ -209,70 +386,21 @@ fn create_player(position: Vec2, spritesheet: Texture2D) -> Entity<Creature, Key
             },
             6.0,
             5.0,
-            CreatureAnimations { idle, walk, jump, fall },
+            3.0,
+            animations,
         ),
-        controller: KeyboardController::new(),
-    }
+        controller,
+    )
 }
 
-fn create_cat(position: Vec2, spritesheet: Texture2D) -> Entity<Creature, RandomController> {
-    let fw = 40;
-    let fh = 32;
-    let idle = Animation {
-        id: NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        sprite: Sprite {
-            spritesheet,
-            offset: UVec2::ZERO,
-            frame_width: fw,
-            frame_height: fh,
-            num_frames_x: 1,
-            num_frames_y: 1,
-        },
-        repeat: true,
-        frame_time: 0.0,
-    };
-    let walk = Animation {
-        id: NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        sprite: Sprite {
-            spritesheet,
-            offset: UVec2::new(0, fh),
-            frame_width: fw,
-            frame_height: fh,
-            num_frames_x: 6,
-            num_frames_y: 1,
-        },
-        repeat: true,
-        frame_time: 0.7,
-    };
-    let jump = Animation {
-        id: NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        sprite: Sprite {
-            spritesheet,
-            offset: UVec2::ZERO,
-            frame_width: fw,
-            frame_height: fh,
-            num_frames_x: 1,
-            num_frames_y: 1,
-        },
-        repeat: true,
-        frame_time: 0.6,
-    };
-    let fall = Animation {
-        id: NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        sprite: Sprite {
-            spritesheet,
-            offset: UVec2::ZERO,
-            frame_width: fw,
-            frame_height: fh,
-            num_frames_x: 1,
-            num_frames_y: 1,
-        },
-        repeat: true,
-        frame_time: 0.5,
-    };
-
-    Entity {
-        object: Creature::new(
+fn create_cat(
+    position: Vec2,
+    move_time: f32,
+    seed: u64,
+    animations: CreatureAnimations,
+) -> CatEntity {
+    Entity::new(
+        Creature::new(
             position,
             Rect::new(2.0, 2.0, 38.0, 30.0),
             Rect::new(0.0, 0.0, 32.0, 32.0),
@@ -285,32 +413,16 @@ fn create_cat(position: Vec2, spritesheet: Texture2D) -> Entity<Creature, Random
             },
             6.0,
             5.0,
-            CreatureAnimations { idle, walk, jump, fall },
+            1.0,
+            animations,
         ),
-        controller: RandomController::new(0.5),
-    }
+        RandomController::new(move_time, seed),
+    )
 }
 
-fn create_lava_block(
-    pos: Vec2,
-    spritesheet: Texture2D,
-) -> Entity<DamageBlock, NoneController<DamageBlock>> {
-    let lava_animation = Animation {
-        id: NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        sprite: Sprite {
-            spritesheet,
-            offset: UVec2::ZERO,
-            frame_width: 32,
-            frame_height: 32,
-            num_frames_x: 5,
-            num_frames_y: 1,
-        },
-        frame_time: 0.06,
-        repeat: true,
-    };
-
-    Entity {
-        object: DamageBlock::new(Rect::new(pos.x, pos.y, 32.0, 32.0), 1.0, lava_animation),
-        controller: NoneController::<DamageBlock>::new(),
-    }
+fn create_lava_block(pos: Vec2, damage: f32, animation: Animation) -> LavaEntity {
+    Entity::new(
+        DamageBlock::new(Rect::new(pos.x, pos.y, 32.0, 32.0), damage, animation),
+        NoneController::<DamageBlock>::new(),
+    )
 }
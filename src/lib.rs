@@ -0,0 +1,15 @@
+pub mod bitfont;
+pub mod camera;
+pub mod collision;
+pub mod controller;
+pub mod entity;
+#[cfg(test)]
+mod fixtures;
+pub mod game;
+pub mod map;
+pub mod scene;
+pub mod screen;
+pub mod sprite;
+pub mod tiled;
+pub mod tiles;
+pub mod timestep;
@@ -1,21 +1,195 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
 use macroquad::prelude::*;
 
-mod bitfont;
-mod controller;
-mod entity;
-mod game;
-mod map;
-mod sprite;
-mod tiles;
-
-#[macroquad::main("Platformer")]
-async fn main() {
-    let mut game = game::Game::new().await.unwrap();
+use platformer::{
+    bitfont::BitFont,
+    controller::{
+        input::{Bindings, DeviceInput, Input},
+        replay::{InputRecorder, Recording, ReplayController},
+        KeyboardController, PlayerController,
+    },
+    game,
+    scene::{gameplay::GameScene, menu::TitleScene, SceneStack},
+    screen::{ScaleMode, VirtualScreen},
+    timestep,
+};
+
+/// The default number of times per second the game world is updated
+static TICKS_PER_SECOND: u32 = 60;
+
+/// The resolution the game is drawn at, before it is scaled up to fit the window
+static VIRTUAL_WIDTH: u32 = 960;
+static VIRTUAL_HEIGHT: u32 = 540;
+
+/// Options passed on the command line
+struct Options {
+    /// The map to play, either a binary map or a Tiled map
+    map: PathBuf,
+    /// Record the player's input to this file
+    record: Option<PathBuf>,
+    /// Replay the player's input from this file
+    replay: Option<PathBuf>,
+    /// The config file for the controls bound to each action
+    bindings: PathBuf,
+    /// How the game is scaled up to fit the window
+    scale: ScaleMode,
+    /// The number of times per second the game world is updated, if it was set
+    tick_rate: Option<u32>,
+}
+
+impl Options {
+    fn from_args() -> anyhow::Result<Self> {
+        let mut options = Options {
+            map: "./assets/maps/map1".into(),
+            record: None,
+            replay: None,
+            bindings: "./assets/bindings.json".into(),
+            scale: ScaleMode::Integer,
+            tick_rate: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--map" => {
+                    let path = args.next().ok_or_else(|| anyhow::anyhow!("--map needs a path"))?;
+                    options.map = path.into();
+                }
+                "--bindings" => {
+                    let path =
+                        args.next().ok_or_else(|| anyhow::anyhow!("--bindings needs a path"))?;
+                    options.bindings = path.into();
+                }
+                "--scale" => {
+                    let mode =
+                        args.next().ok_or_else(|| anyhow::anyhow!("--scale needs a mode"))?;
+                    options.scale = mode.parse()?;
+                }
+                "--tick-rate" => {
+                    let rate =
+                        args.next().ok_or_else(|| anyhow::anyhow!("--tick-rate needs a number"))?;
+                    match rate.parse() {
+                        Ok(rate) if rate > 0 => options.tick_rate = Some(rate),
+                        _ => {
+                            anyhow::bail!("Invalid tick rate '{rate}', expected a positive number")
+                        }
+                    }
+                }
+                "--record" => {
+                    let path =
+                        args.next().ok_or_else(|| anyhow::anyhow!("--record needs a path"))?;
+                    options.record = Some(path.into());
+                }
+                "--replay" => {
+                    let path =
+                        args.next().ok_or_else(|| anyhow::anyhow!("--replay needs a path"))?;
+                    options.replay = Some(path.into());
+                }
+                _ => anyhow::bail!("Unknown argument: {arg}"),
+            }
+        }
+        if options.record.is_some() && options.replay.is_some() {
+            anyhow::bail!("--record and --replay can not be used together");
+        }
+        Ok(options)
+    }
+
+    /// Creates the player's controller, and works out the seed and tick rate for the session.
+    /// Replays use the tick rate they were recorded at.
+    /// # Arguments
+    /// `bindings` - The controls bound to each action
+    /// # Return
+    /// Returns the controller, the seed and the number of ticks per second
+    fn player_controller(
+        &self,
+        bindings: &Bindings,
+    ) -> anyhow::Result<(PlayerController, u64, u32)> {
+        if let Some(path) = &self.replay {
+            let recording = Recording::load(path)?;
+            match self.tick_rate {
+                Some(tick_rate) if tick_rate != recording.tick_rate => anyhow::bail!(
+                    "Replay was recorded at {} ticks per second, but --tick-rate is {tick_rate}",
+                    recording.tick_rate
+                ),
+                _ => {}
+            }
+            let (seed, tick_rate) = (recording.seed, recording.tick_rate);
+            let controller = PlayerController::Replay(ReplayController::new(recording));
+            return Ok((controller, seed, tick_rate));
+        }
+
+        let seed = ::rand::random();
+        let tick_rate = self.tick_rate.unwrap_or(TICKS_PER_SECOND);
+        let keyboard = KeyboardController::new(Input::new(bindings.clone(), DeviceInput::new()));
+        let controller = match self.record {
+            Some(_) => PlayerController::Recorder(InputRecorder::new(
+                keyboard,
+                Recording::new(tick_rate, seed),
+            )),
+            None => PlayerController::Keyboard(keyboard),
+        };
+        Ok((controller, seed, tick_rate))
+    }
+}
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "Platformer".into(),
+        window_width: VIRTUAL_WIDTH as i32,
+        window_height: VIRTUAL_HEIGHT as i32,
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
+async fn main() -> anyhow::Result<()> {
+    let options = Options::from_args()?;
+    let bindings = Bindings::load(&options.bindings)?;
+    let (player_controller, seed, tick_rate) = options.player_controller(&bindings)?;
+    let mut menu_input = Input::new(bindings.clone(), DeviceInput::new());
+
+    // Keep the window open long enough to save the recording when the game is closed
+    if options.record.is_some() {
+        prevent_quit();
+    }
+
+    let font_spritesheet =
+        load_texture("./assets/fonts/Victoria.png").await.context("Failed to load font")?;
+    let font = BitFont::new(32, 96, 8, 9, font_spritesheet);
+
+    let screen = VirtualScreen::new(VIRTUAL_WIDTH, VIRTUAL_HEIGHT, options.scale);
+    let game =
+        game::Game::new(&options.map, player_controller, seed, font.clone(), screen.size()).await?;
+    let mut scenes = SceneStack::new(Box::new(GameScene::new(
+        game,
+        font.clone(),
+        bindings.clone(),
+        options.record.clone(),
+    )));
+    // Replays start straight away, so that they play back the same as they were recorded
+    if options.replay.is_none() {
+        scenes.push(Box::new(TitleScene::new(font, &bindings)));
+    }
+
+    let mut timestep = timestep::FixedTimestep::new(tick_rate);
     loop {
-        let dt = get_frame_time();
+        if is_quit_requested() {
+            scenes.quit()?;
+        }
+        menu_input.update();
+        scenes.handle_input(&menu_input)?;
 
-        game.update(dt);
-        game.draw();
+        let ticks = timestep.advance(get_frame_time());
+        for _ in 0..ticks {
+            scenes.update(timestep.dt())?;
+        }
+        if scenes.is_empty() {
+            return Ok(());
+        }
+        screen.begin();
+        scenes.draw(screen.size(), timestep.alpha());
+        screen.end();
 
         next_frame().await
     }
@@ -1,60 +1,336 @@
-use std::{fs::File, io::Read, path::Path};
+use std::{collections::BTreeMap, fs::File, io::Write, ops::Range, path::Path, str::FromStr};
 
 use anyhow::Context;
-use macroquad::prelude::{Rect, Vec2};
+use macroquad::{
+    prelude::{Rect, Vec2, WHITE},
+    texture::{draw_texture, Texture2D},
+};
 
-use crate::tiles::{TileInfo, TileSet};
+use crate::tiles::{TileCollision, TileInfo, TileSet};
+
+static MAGIC_ID: [u8; 3] = *b"MAP";
+static VERSION: u8 = 2;
+
+/// The id of the tile used for empty space
+pub static EMPTY_TILE: u16 = 0;
+
+/// The role a tile layer plays in the map
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LayerKind {
+    /// Drawn behind everything, and has no effect on physics
+    Background,
+    /// The tiles that objects collide with. This is drawn behind objects.
+    Collision,
+    /// Drawn in front of everything, and has no effect on physics
+    Foreground,
+}
+
+impl LayerKind {
+    fn from_id(id: u8) -> anyhow::Result<Self> {
+        match id {
+            0 => Ok(LayerKind::Background),
+            1 => Ok(LayerKind::Collision),
+            2 => Ok(LayerKind::Foreground),
+            _ => anyhow::bail!("Invalid layer kind: {id}"),
+        }
+    }
+
+    fn id(self) -> u8 {
+        match self {
+            LayerKind::Background => 0,
+            LayerKind::Collision => 1,
+            LayerKind::Foreground => 2,
+        }
+    }
+}
+
+/// A grid of tiles that covers the whole map
+pub struct TileLayer {
+    pub kind: LayerKind,
+    pub tiles: Vec<u16>,
+}
+
+/// An object to spawn when the map is loaded
+#[derive(Clone)]
+pub struct ObjectSpawn {
+    /// The type of object to spawn, e.g. "cat" or "lava"
+    pub kind: String,
+    /// The top left corner of the object (in pixels)
+    pub position: Vec2,
+    /// Extra properties used to configure the object
+    pub properties: BTreeMap<String, String>,
+}
+
+impl ObjectSpawn {
+    /// Gets a property of the object, parsed as `T`
+    /// # Arguments
+    /// `key` - The name of the property
+    /// `default` - The value to use if the object doesn't have the property
+    /// # Return
+    /// Returns the value of the property, or an error if the property couldn't be parsed
+    pub fn property<T: FromStr>(&self, key: &str, default: T) -> anyhow::Result<T> {
+        match self.properties.get(key) {
+            Some(value) => value.parse().map_err(|_| {
+                anyhow::anyhow!("Invalid value for property '{key}' of '{}': {value}", self.kind)
+            }),
+            None => Ok(default),
+        }
+    }
+}
+
+/// An image drawn behind the tile layers that scrolls at its own rate, giving the map depth
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParallaxLayer {
+    /// The path to the image, relative to the map file
+    pub texture: String,
+    /// How far the layer moves compared to the camera on each axis. 0.0 keeps the layer fixed to
+    /// the screen, and 1.0 moves it along with the map.
+    pub scroll: Vec2,
+    /// The position of the top left corner of the layer when the camera is at the top left of
+    /// the map (in pixels)
+    pub offset: Vec2,
+    /// Whether the image is repeated to fill the screen horizontally
+    pub repeat_x: bool,
+    /// Whether the image is repeated to fill the screen vertically
+    pub repeat_y: bool,
+}
+
+impl ParallaxLayer {
+    /// Works out where copies of the layer's image are drawn on the screen
+    /// # Arguments
+    /// `image_size` - The size of the layer's image in pixels
+    /// `viewport` - The visible area of the map
+    /// # Return
+    /// Returns the top left corner of every visible copy of the image, relative to the viewport
+    pub fn placements(&self, image_size: Vec2, viewport: Rect) -> Vec<Vec2> {
+        let origin = (self.offset - viewport.point() * self.scroll).round();
+        let axis = |origin: f32, size: f32, view: f32, repeat: bool| -> Vec<f32> {
+            if size <= 0.0 {
+                vec![]
+            }
+            else if repeat {
+                // Start from the last copy that begins at or before the edge of the screen
+                let start = origin - (origin / size).ceil() * size;
+                let count = ((view - start) / size).ceil().max(0.0) as usize;
+                (0..count).map(|i| start + i as f32 * size).collect()
+            }
+            else if origin + size > 0.0 && origin < view {
+                vec![origin]
+            }
+            else {
+                vec![]
+            }
+        };
+
+        let xs = axis(origin.x, image_size.x, viewport.w, self.repeat_x);
+        let ys = axis(origin.y, image_size.y, viewport.h, self.repeat_y);
+        ys.iter().flat_map(|&y| xs.iter().map(move |&x| Vec2::new(x, y))).collect()
+    }
+}
 
 pub struct Map {
     pub width: usize,
     pub height: usize,
-    tiles: Vec<u16>,
+    /// The name of the map
+    pub name: String,
+    /// Where the player starts in the map (in pixels)
+    pub spawn: Option<Vec2>,
+    /// The path to the tileset descriptor used by the map, relative to the map file
+    pub tileset_path: Option<String>,
+    /// The objects placed in the map
+    pub objects: Vec<ObjectSpawn>,
+    /// The images drawn behind the tile layers, from back to front
+    pub parallax: Vec<ParallaxLayer>,
+    /// The tile layers of the map, in drawing order
+    layers: Vec<TileLayer>,
+    /// The index of the collision layer in `layers`
+    collision: usize,
     tileset: TileSet,
+    /// The seconds since the map was loaded, which drives tile animations
+    time: f32,
 }
 
 impl Map {
-    /// Loads a map from a file
-    pub fn load_map(path: &Path, tileset: TileSet) -> anyhow::Result<Self> {
-        static VERSION: u8 = 1;
-        static MAGIC_ID: [u8; 3] = *b"MAP";
-
-        let mut file =
-            File::open(path).with_context(|| format!("failed to open: {}", path.display()))?;
+    /// Creates a map with a single collision layer from a row-major list of tile ids
+    pub fn new(width: usize, height: usize, tiles: Vec<u16>, tileset: TileSet) -> Self {
+        let layers = vec![TileLayer { kind: LayerKind::Collision, tiles }];
+        Self::with_layers(width, height, layers, tileset).unwrap()
+    }
 
-        let mut header = [0; 12];
-        // Load header into the buffer
-        match file.read(&mut header) {
-            Ok(n) if n == 12 => {}
-            _ => anyhow::bail!("Could not read file header"),
+    /// Creates a map from a list of tile layers. There must be exactly one collision layer and
+    /// every layer must have `width * height` tiles.
+    pub fn with_layers(
+        width: usize,
+        height: usize,
+        layers: Vec<TileLayer>,
+        tileset: TileSet,
+    ) -> anyhow::Result<Self> {
+        for layer in &layers {
+            if layer.tiles.len() != width * height {
+                anyhow::bail!(
+                    "Invalid number of tiles in {:?} layer, expected: {}, but found: {}",
+                    layer.kind,
+                    width * height,
+                    layer.tiles.len()
+                );
+            }
         }
 
+        let mut collision_layers =
+            layers.iter().enumerate().filter(|(_, layer)| layer.kind == LayerKind::Collision);
+        let collision = match (collision_layers.next(), collision_layers.next()) {
+            (Some((i, _)), None) => i,
+            (None, _) => anyhow::bail!("Map has no collision layer"),
+            (Some(_), Some(_)) => anyhow::bail!("Map has more than one collision layer"),
+        };
+
+        Ok(Self {
+            width,
+            height,
+            name: String::new(),
+            spawn: None,
+            tileset_path: None,
+            objects: vec![],
+            parallax: vec![],
+            layers,
+            collision,
+            tileset,
+            time: 0.0,
+        })
+    }
+
+    /// Loads a map from a file. See `docs/map_format.txt` for a description of the format.
+    /// # Arguments
+    /// `path` - The path to the map file
+    /// `tileset` - The tileset to use, or `None` to load the tileset that the map refers to
+    pub fn load_map(path: &Path, tileset: Option<TileSet>) -> anyhow::Result<Self> {
+        let mut map = Self::read_map(path)?;
+        map.tileset = match (tileset, &map.tileset_path) {
+            (Some(tileset), _) => tileset,
+            (None, Some(tileset_path)) => {
+                TileSet::load(&path.parent().unwrap_or(Path::new("")).join(tileset_path))?
+            }
+            (None, None) => anyhow::bail!("{} does not refer to a tileset", path.display()),
+        };
+        map.check_tiles()?;
+        Ok(map)
+    }
+
+    /// Reads a map from a file without loading its tileset. This is useful for tools that only
+    /// work with the tile ids, but the map can't be used for physics or drawing.
+    pub fn read_map(path: &Path) -> anyhow::Result<Self> {
+        let data =
+            std::fs::read(path).with_context(|| format!("failed to open: {}", path.display()))?;
+        let mut reader = ByteReader::new(&data);
+
+        // Load the header
+        let header = reader.bytes(12).context("Could not read file header")?;
+
         // Check the magic id
-        if &header[0..3] != &MAGIC_ID {
+        if header[0..3] != MAGIC_ID {
             anyhow::bail!("Invalid magic id");
         }
 
-        // Check the version number
-        if header[3] != VERSION {
-            anyhow::bail!("Invalid map version");
-        }
-
         // Get the width and height of the map
         let width = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
         let height = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
 
-        // Read the tiles
-        let length = width * height * 2;
-        let mut tile_buffer = vec![0; length];
-        match file.read(&mut tile_buffer) {
-            Ok(n) if n == length => {}
-            Ok(n) => anyhow::bail!("Invalid number of tiles, expected: {length}, but found: {n}"),
-            _ => anyhow::bail!("Could not load map tiles"),
+        // Check the version number
+        match header[3] {
+            1 => {
+                // Version 1 maps are just a single layer of tiles
+                let tiles = read_tiles(&mut reader, width, height)?;
+                Ok(Map::new(width, height, tiles, TileSet::empty()))
+            }
+            2 => load_chunks(&mut reader, width, height),
+            _ => anyhow::bail!("Invalid map version"),
         }
+    }
 
-        let tiles =
-            tile_buffer.chunks(2).map(|x| u16::from_le_bytes(x.try_into().unwrap())).collect();
+    /// Checks that every tile in the map is part of the tileset
+    fn check_tiles(&self) -> anyhow::Result<()> {
+        for layer in &self.layers {
+            if let Some(tile) =
+                layer.tiles.iter().find(|&&tile| tile as usize >= self.tileset.tile_info.len())
+            {
+                anyhow::bail!(
+                    "{:?} layer uses tile {tile}, but the tileset only has {} tiles",
+                    layer.kind,
+                    self.tileset.tile_info.len()
+                );
+            }
+        }
+        Ok(())
+    }
 
-        Ok(Self { tiles, width, height, tileset })
+    /// Saves the map to a file, using the latest version of the map format
+    pub fn save_map(&self, path: &Path) -> anyhow::Result<()> {
+        let mut data = vec![];
+        data.extend_from_slice(&MAGIC_ID);
+        data.push(VERSION);
+        data.extend_from_slice(&(self.width as u32).to_le_bytes());
+        data.extend_from_slice(&(self.height as u32).to_le_bytes());
+
+        write_chunk(&mut data, b"NAME", self.name.as_bytes());
+        if let Some(spawn) = self.spawn {
+            let mut chunk = spawn.x.to_le_bytes().to_vec();
+            chunk.extend_from_slice(&spawn.y.to_le_bytes());
+            write_chunk(&mut data, b"SPWN", &chunk);
+        }
+        if let Some(tileset_path) = &self.tileset_path {
+            write_chunk(&mut data, b"TSET", tileset_path.as_bytes());
+        }
+        if !self.objects.is_empty() {
+            let Ok(num_objects) = u32::try_from(self.objects.len())
+            else {
+                anyhow::bail!("Too many objects to save: {}", self.objects.len());
+            };
+            let mut chunk = num_objects.to_le_bytes().to_vec();
+            for object in &self.objects {
+                write_string(&mut chunk, &object.kind)?;
+                chunk.extend_from_slice(&object.position.x.to_le_bytes());
+                chunk.extend_from_slice(&object.position.y.to_le_bytes());
+                let Ok(num_properties) = u16::try_from(object.properties.len())
+                else {
+                    anyhow::bail!(
+                        "Too many properties to save on '{}': {}",
+                        object.kind,
+                        object.properties.len()
+                    );
+                };
+                chunk.extend_from_slice(&num_properties.to_le_bytes());
+                for (key, value) in &object.properties {
+                    write_string(&mut chunk, key)?;
+                    write_string(&mut chunk, value)?;
+                }
+            }
+            write_chunk(&mut data, b"OBJS", &chunk);
+        }
+        for layer in &self.parallax {
+            let mut chunk = vec![];
+            write_string(&mut chunk, &layer.texture)?;
+            for value in [layer.scroll.x, layer.scroll.y, layer.offset.x, layer.offset.y] {
+                chunk.extend_from_slice(&value.to_le_bytes());
+            }
+            chunk.push(layer.repeat_x as u8 | (layer.repeat_y as u8) << 1);
+            write_chunk(&mut data, b"PRLX", &chunk);
+        }
+        for layer in &self.layers {
+            let mut chunk = vec![layer.kind.id()];
+            chunk.extend(layer.tiles.iter().flat_map(|tile| tile.to_le_bytes()));
+            write_chunk(&mut data, b"LAYR", &chunk);
+        }
+
+        let mut file =
+            File::create(path).with_context(|| format!("failed to create: {}", path.display()))?;
+        file.write_all(&data).with_context(|| format!("failed to write: {}", path.display()))
+    }
+
+    /// Advances the clock used by tile animations
+    /// # Arguments
+    /// `secs` - The total seconds elapsed since the last update
+    pub fn update(&mut self, secs: f32) {
+        self.time += secs;
     }
 
     pub fn size(&self) -> Vec2 {
@@ -62,6 +338,10 @@ impl Map {
         Vec2::new(self.width as f32 * tile_size, self.height as f32 * tile_size)
     }
 
+    pub fn tileset(&self) -> &TileSet {
+        &self.tileset
+    }
+
     pub fn tile_size(&self) -> i32 {
         self.tileset.tile_size
     }
@@ -70,15 +350,138 @@ impl Map {
         self.tileset.id(self.get(x, y))
     }
 
+    /// Gets the friction of the ground under an area of the map
+    /// # Arguments
+    /// `bounds` - The area to check below, in pixels
+    /// # Return
+    /// Returns the average friction of the tiles that can be stood on in the row directly below the
+    /// bounds, or 1.0 if there are no such tiles there
+    pub fn ground_friction(&self, bounds: Rect) -> f32 {
+        let tile_size = self.tile_size() as f32;
+        // Sample slightly below the bounds, so an object resting on the ground is not thrown off by
+        // rounding errors in its position
+        let y = ((bounds.bottom() + 0.5) / tile_size).floor();
+        if y < 0.0 || y >= self.height as f32 {
+            return 1.0;
+        }
+        let start_x = (bounds.left() / tile_size).floor().max(0.0) as usize;
+        let end_x = ((bounds.right() / tile_size).ceil().max(0.0) as usize).min(self.width);
+
+        let (total, count) = (start_x..end_x)
+            .map(|x| self.tile_info_at(x, y as usize))
+            .filter(|info| info.is_ground())
+            .fold((0.0, 0), |(total, count), info| (total + info.friction, count + 1));
+        if count == 0 {
+            1.0
+        }
+        else {
+            total / count as f32
+        }
+    }
+
+    /// Finds the ground surface at a point, made from the tops of solid tiles and the surfaces of
+    /// slopes. Only surfaces within half a tile above or below the point are considered.
+    /// # Arguments
+    /// `x` - The horizontal position to check, in pixels
+    /// `y` - The vertical position to search around, in pixels
+    /// # Return
+    /// Returns the y position of the highest surface near the point along with the collision kind
+    /// of its tile, or `None` if there is no ground there
+    pub fn ground_surface(&self, x: f32, y: f32) -> Option<(f32, TileCollision)> {
+        let tile_size = self.tile_size() as f32;
+        let tile_x = (x / tile_size).floor();
+        if tile_x < 0.0 || tile_x >= self.width as f32 || self.height == 0 {
+            return None;
+        }
+        let top = ((y - tile_size / 2.0) / tile_size).floor().max(0.0) as usize;
+        let bottom = ((y + tile_size / 2.0) / tile_size).floor().max(0.0) as usize;
+
+        (top..=bottom.min(self.height - 1))
+            .filter_map(|tile_y| {
+                let collision = self.tile_info_at(tile_x as usize, tile_y).collision;
+                let height = match collision {
+                    TileCollision::Solid => 1.0,
+                    TileCollision::Slope(slope) => slope.height_at(x / tile_size - tile_x),
+                    _ => return None,
+                };
+                Some(((tile_y as f32 + 1.0 - height) * tile_size, collision))
+            })
+            .filter(|(surface, _)| (surface - y).abs() <= tile_size / 2.0)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    /// Gets the tile layers of the map, in drawing order
+    pub fn layers(&self) -> &[TileLayer] {
+        &self.layers
+    }
+
     fn get(&self, x: usize, y: usize) -> u16 {
         assert!(x < self.width);
         assert!(y < self.height);
-        self.tiles[x + y * self.width]
+        self.layers[self.collision].tiles[x + y * self.width]
+    }
+
+    /// Gets the tiles that are at least partly inside an area
+    /// # Arguments
+    /// `area` - The area in pixels
+    /// # Return
+    /// Returns the range of columns and the range of rows of the tiles
+    pub fn tiles_in(&self, area: Rect) -> (Range<usize>, Range<usize>) {
+        let tile_size = self.tile_size() as f32;
+        let range = |start: f32, end: f32, len: usize| {
+            let first = (start / tile_size).floor().clamp(0.0, len as f32) as usize;
+            let last = (end / tile_size).ceil().clamp(0.0, len as f32) as usize;
+            first..last.max(first)
+        };
+        (
+            range(area.left(), area.right(), self.width),
+            range(area.top(), area.bottom(), self.height),
+        )
+    }
+
+    /// Draws the parallax layers, which appear behind everything else
+    /// # Arguments
+    /// `textures` - The image of each parallax layer, in the same order as `parallax`
+    /// `viewport` - The visible area of the map
+    pub fn draw_parallax(&self, textures: &[Texture2D], viewport: Rect) {
+        for (layer, &texture) in self.parallax.iter().zip(textures) {
+            let image_size = Vec2::new(texture.width(), texture.height());
+            for position in layer.placements(image_size, viewport) {
+                draw_texture(texture, position.x, position.y, WHITE);
+            }
+        }
+    }
+
+    /// Draws the layers that appear behind objects (the background and collision layers)
+    /// # Arguments
+    /// `texture` - The tileset texture
+    /// `viewport` - The visible area of the map
+    pub fn draw_background(&self, texture: Texture2D, viewport: Rect) {
+        for layer in self.layers.iter().filter(|layer| layer.kind != LayerKind::Foreground) {
+            self.draw_layer(layer, texture, viewport);
+        }
+    }
+
+    /// Draws the layers that appear in front of objects
+    /// # Arguments
+    /// `texture` - The tileset texture
+    /// `viewport` - The visible area of the map
+    pub fn draw_foreground(&self, texture: Texture2D, viewport: Rect) {
+        for layer in self.layers.iter().filter(|layer| layer.kind == LayerKind::Foreground) {
+            self.draw_layer(layer, texture, viewport);
+        }
     }
 
-    pub fn draw(&self, camera: Vec2) {
-        for tile_x in 0..self.width {
-            for tile_y in 0..self.height {
+    fn draw_layer(&self, layer: &TileLayer, texture: Texture2D, viewport: Rect) {
+        let camera = viewport.point();
+        let (columns, rows) = self.tiles_in(viewport);
+        for tile_x in columns {
+            for tile_y in rows.clone() {
+                let tile = layer.tiles[tile_x + tile_y * self.width];
+                if tile == EMPTY_TILE {
+                    continue;
+                }
+                let tile = self.tileset.frame_at(tile, self.time);
                 let x = (tile_x * self.tile_size() as usize) as f32;
                 let y = (tile_y * self.tile_size() as usize) as f32;
                 let dest_rect = Rect::new(
@@ -87,8 +490,351 @@ impl Map {
                     self.tile_size() as f32,
                     self.tile_size() as f32,
                 );
-                self.tileset.draw(self.get(tile_x, tile_y), dest_rect);
+                self.tileset.draw(texture, tile, dest_rect);
             }
         }
     }
 }
+
+/// Reads the chunks of a version 2 map
+fn load_chunks(reader: &mut ByteReader, width: usize, height: usize) -> anyhow::Result<Map> {
+    let mut name = String::new();
+    let mut spawn = None;
+    let mut tileset_path = None;
+    let mut objects = vec![];
+    let mut parallax = vec![];
+    let mut layers = vec![];
+
+    while !reader.is_empty() {
+        let id = reader.bytes(4).context("Could not read chunk id")?;
+        let length = reader.u32().context("Could not read chunk length")? as usize;
+        let mut chunk = ByteReader::new(
+            reader
+                .bytes(length)
+                .with_context(|| format!("Chunk {} is truncated", String::from_utf8_lossy(id)))?,
+        );
+
+        match id {
+            b"NAME" => name = chunk.string()?,
+            b"SPWN" => spawn = Some(Vec2::new(chunk.f32()?, chunk.f32()?)),
+            b"TSET" => tileset_path = Some(chunk.string()?),
+            b"OBJS" => {
+                let count = chunk.u32()?;
+                for _ in 0..count {
+                    let kind = chunk.short_string()?;
+                    let position = Vec2::new(chunk.f32()?, chunk.f32()?);
+                    let mut properties = BTreeMap::new();
+                    for _ in 0..chunk.u16()? {
+                        let key = chunk.short_string()?;
+                        properties.insert(key, chunk.short_string()?);
+                    }
+                    objects.push(ObjectSpawn { kind, position, properties });
+                }
+            }
+            b"PRLX" => {
+                let texture = chunk.short_string()?;
+                let scroll = Vec2::new(chunk.f32()?, chunk.f32()?);
+                let offset = Vec2::new(chunk.f32()?, chunk.f32()?);
+                let repeat = chunk.u8()?;
+                parallax.push(ParallaxLayer {
+                    texture,
+                    scroll,
+                    offset,
+                    repeat_x: repeat & 1 != 0,
+                    repeat_y: repeat & 2 != 0,
+                });
+            }
+            b"LAYR" => {
+                let kind = LayerKind::from_id(chunk.u8()?)?;
+                let tiles = read_tiles(&mut chunk, width, height)?;
+                layers.push(TileLayer { kind, tiles });
+            }
+            // Skip chunks from newer versions of the format
+            _ => {}
+        }
+    }
+
+    let mut map = Map::with_layers(width, height, layers, TileSet::empty())?;
+    map.name = name;
+    map.spawn = spawn;
+    map.tileset_path = tileset_path;
+    map.objects = objects;
+    map.parallax = parallax;
+    Ok(map)
+}
+
+/// Reads `width * height` tiles
+fn read_tiles(reader: &mut ByteReader, width: usize, height: usize) -> anyhow::Result<Vec<u16>> {
+    let Some(length) = width.checked_mul(height).and_then(|tiles| tiles.checked_mul(2))
+    else {
+        anyhow::bail!("Invalid map size: {width}x{height}");
+    };
+    let tile_buffer = match reader.bytes(length) {
+        Ok(buffer) => buffer,
+        Err(_) => anyhow::bail!(
+            "Invalid number of tiles, expected: {length}, but found: {}",
+            reader.remaining()
+        ),
+    };
+    Ok(tile_buffer.chunks(2).map(|x| u16::from_le_bytes(x.try_into().unwrap())).collect())
+}
+
+fn write_chunk(data: &mut Vec<u8>, id: &[u8; 4], chunk: &[u8]) {
+    data.extend_from_slice(id);
+    data.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+    data.extend_from_slice(chunk);
+}
+
+/// Writes a string prefixed by its length. The length is stored as a u16, so longer strings are
+/// an error.
+fn write_string(data: &mut Vec<u8>, string: &str) -> anyhow::Result<()> {
+    let Ok(len) = u16::try_from(string.len())
+    else {
+        anyhow::bail!("String is too long to save: {} bytes", string.len());
+    };
+    data.extend_from_slice(&len.to_le_bytes());
+    data.extend_from_slice(string.as_bytes());
+    Ok(())
+}
+
+/// A cursor for reading little endian values from a byte buffer
+struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    fn bytes(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if self.data.len() < n {
+            anyhow::bail!("Unexpected end of file");
+        }
+        let (bytes, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    /// Reads the rest of the buffer as a UTF-8 string
+    fn string(&mut self) -> anyhow::Result<String> {
+        let bytes = self.bytes(self.remaining())?;
+        String::from_utf8(bytes.to_vec()).context("Invalid UTF-8 string")
+    }
+
+    /// Reads a string prefixed by its length
+    fn short_string(&mut self) -> anyhow::Result<String> {
+        let length = self.u16()? as usize;
+        String::from_utf8(self.bytes(length)?.to_vec()).context("Invalid UTF-8 string")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_tiles_in_an_area() {
+        let map = Map::new(10, 5, vec![0; 50], TileSet::empty());
+        let size = map.tile_size() as f32;
+
+        assert_eq!(map.tiles_in(Rect::new(0.0, 0.0, size, size)), (0..1, 0..1));
+        assert_eq!(map.tiles_in(Rect::new(size / 2.0, size, size, size * 2.5)), (0..2, 1..4));
+        // Areas are clamped to the map
+        assert_eq!(map.tiles_in(Rect::new(-100.0, -100.0, 1e6, 1e6)), (0..10, 0..5));
+        assert_eq!(map.tiles_in(Rect::new(-100.0, 1e5, 50.0, 50.0)), (0..0, 5..5));
+    }
+
+    #[test]
+    fn places_parallax_layers() {
+        let layer = ParallaxLayer {
+            texture: "sky.png".into(),
+            scroll: Vec2::new(0.5, 0.0),
+            offset: Vec2::new(10.0, 20.0),
+            repeat_x: false,
+            repeat_y: false,
+        };
+        let image = Vec2::new(100.0, 50.0);
+        let view = Rect::new(0.0, 0.0, 150.0, 100.0);
+        assert_eq!(layer.placements(image, view), vec![Vec2::new(10.0, 20.0)]);
+
+        // The layer moves at half the speed of the camera, and is not drawn once it is offscreen
+        let moved = Rect::new(100.0, 300.0, 150.0, 100.0);
+        assert_eq!(layer.placements(image, moved), vec![Vec2::new(-40.0, 20.0)]);
+        assert_eq!(layer.placements(image, Rect::new(300.0, 0.0, 150.0, 100.0)), vec![]);
+
+        // Repeated layers fill the whole view
+        let layer = ParallaxLayer { repeat_x: true, repeat_y: true, ..layer };
+        assert_eq!(layer.placements(image, moved), vec![
+            Vec2::new(-40.0, -30.0),
+            Vec2::new(60.0, -30.0),
+            Vec2::new(-40.0, 20.0),
+            Vec2::new(60.0, 20.0),
+            Vec2::new(-40.0, 70.0),
+            Vec2::new(60.0, 70.0),
+        ]);
+    }
+
+    #[test]
+    fn saves_parallax_layers() {
+        let mut map = Map::new(2, 2, vec![0; 4], TileSet::empty());
+        map.parallax = vec![ParallaxLayer {
+            texture: "../sky.png".into(),
+            scroll: Vec2::new(0.25, 0.5),
+            offset: Vec2::new(-8.0, 16.0),
+            repeat_x: true,
+            repeat_y: false,
+        }];
+        let loaded = round_trip(&map, "parallax").unwrap();
+        assert_eq!(loaded.parallax, map.parallax);
+    }
+
+    /// Gets a path for a temporary map file that is unique to the test
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{name}-{}.map", std::process::id()))
+    }
+
+    /// Saves a map to a temporary file and reads it back
+    fn round_trip(map: &Map, name: &str) -> anyhow::Result<Map> {
+        let path = temp_path(name);
+        map.save_map(&path)?;
+        let loaded = Map::read_map(&path);
+        std::fs::remove_file(&path)?;
+        loaded
+    }
+
+    #[test]
+    fn loads_version_1_maps() {
+        let mut data = b"MAP\x01".to_vec();
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend([0u16, 1, 2, 3, 4, 5].iter().flat_map(|tile| tile.to_le_bytes()));
+        let path = temp_path("version-1");
+        std::fs::write(&path, &data).unwrap();
+        let map = Map::read_map(&path);
+        std::fs::remove_file(&path).unwrap();
+        let map = map.unwrap();
+
+        assert_eq!((map.width, map.height), (3, 2));
+        assert_eq!(map.layers().len(), 1);
+        assert_eq!(map.layers()[0].kind, LayerKind::Collision);
+        assert_eq!(map.layers()[0].tiles, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(map.name, "");
+        assert_eq!(map.spawn, None);
+        assert_eq!(map.tileset_path, None);
+    }
+
+    #[test]
+    fn version_2_maps_round_trip() {
+        let layers = vec![
+            TileLayer { kind: LayerKind::Background, tiles: vec![1, 2, 3, 4, 5, 6] },
+            TileLayer { kind: LayerKind::Collision, tiles: vec![0, 0, 7, 7, 0, 0] },
+            TileLayer { kind: LayerKind::Foreground, tiles: vec![8, 0, 0, 0, 0, 9] },
+        ];
+        let mut map = Map::with_layers(3, 2, layers, TileSet::empty()).unwrap();
+        map.name = "Round trip".into();
+        map.spawn = Some(Vec2::new(12.5, -3.0));
+        map.tileset_path = Some("../tileset.json".into());
+        map.objects = vec![ObjectSpawn {
+            kind: "cat".into(),
+            position: Vec2::new(40.0, 8.0),
+            properties: [("move_time".to_string(), "0.25".to_string())].into(),
+        }];
+
+        let loaded = round_trip(&map, "version-2").unwrap();
+        assert_eq!((loaded.width, loaded.height), (3, 2));
+        let layers = |map: &Map| -> Vec<(LayerKind, Vec<u16>)> {
+            map.layers().iter().map(|layer| (layer.kind, layer.tiles.clone())).collect()
+        };
+        assert_eq!(layers(&loaded), layers(&map));
+        assert_eq!(loaded.name, map.name);
+        assert_eq!(loaded.spawn, map.spawn);
+        assert_eq!(loaded.tileset_path, map.tileset_path);
+        assert_eq!(loaded.objects.len(), 1);
+        assert_eq!(loaded.objects[0].kind, "cat");
+        assert_eq!(loaded.objects[0].position, Vec2::new(40.0, 8.0));
+        assert_eq!(loaded.objects[0].properties, map.objects[0].properties);
+
+        // Maps without the optional chunks stay without them
+        let map = Map::new(1, 1, vec![0], TileSet::empty());
+        let loaded = round_trip(&map, "version-2-minimal").unwrap();
+        assert_eq!(loaded.spawn, None);
+        assert_eq!(loaded.tileset_path, None);
+        assert!(loaded.objects.is_empty());
+    }
+
+    #[test]
+    fn refuses_to_save_strings_that_are_too_long() {
+        let mut map = Map::new(1, 1, vec![0], TileSet::empty());
+        map.objects = vec![ObjectSpawn {
+            kind: "cat".into(),
+            position: Vec2::ZERO,
+            properties: [("note".to_string(), "a".repeat(u16::MAX as usize + 1))].into(),
+        }];
+        let path = temp_path("long-string");
+        let err = map.save_map(&path).unwrap_err();
+        assert!(err.to_string().contains("65536 bytes"), "{err}");
+        assert!(!path.exists());
+
+        // The longest string that fits is still saved
+        map.objects[0].properties.insert("note".into(), "a".repeat(u16::MAX as usize));
+        let loaded = round_trip(&map, "longest-string").unwrap();
+        assert_eq!(loaded.objects[0].properties, map.objects[0].properties);
+    }
+
+    #[test]
+    fn refuses_to_save_too_many_properties() {
+        let mut map = Map::new(1, 1, vec![0], TileSet::empty());
+        let properties = (0..=u16::MAX as u32).map(|i| (i.to_string(), String::new())).collect();
+        map.objects = vec![ObjectSpawn { kind: "cat".into(), position: Vec2::ZERO, properties }];
+        let path = temp_path("too-many-properties");
+        let err = map.save_map(&path).unwrap_err();
+        assert_eq!(err.to_string(), "Too many properties to save on 'cat': 65536");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn rejects_map_sizes_that_overflow() {
+        let mut data = b"MAP\x01".to_vec();
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&[0; 8]);
+        let path = temp_path("overflowing-size");
+        std::fs::write(&path, &data).unwrap();
+        let map = Map::read_map(&path);
+        std::fs::remove_file(&path).unwrap();
+        let err = map.err().unwrap();
+        assert_eq!(err.to_string(), format!("Invalid map size: {0}x{0}", u32::MAX));
+    }
+
+    #[test]
+    fn maps_without_rows_have_no_ground() {
+        let map = Map::new(3, 0, vec![], TileSet::empty());
+        assert_eq!(map.ground_surface(16.0, 0.0), None);
+        assert_eq!(map.ground_surface(16.0, 100.0), None);
+    }
+}
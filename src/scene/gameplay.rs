@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use macroquad::prelude::Vec2;
+
+use crate::{
+    bitfont::BitFont,
+    controller::input::{Action, Bindings, Input},
+    game::Game,
+    scene::{
+        menu::{GameOverScene, PauseScene},
+        Scene, Transition,
+    },
+};
+
+/// The scene where the game is played
+pub struct GameScene {
+    game: Game,
+    /// The font for the menus opened from the game
+    font: BitFont,
+    /// The controls shown on the menus opened from the game
+    bindings: Bindings,
+    /// Where to save the player's recorded input when the game ends
+    record_path: Option<PathBuf>,
+}
+
+impl GameScene {
+    pub fn new(
+        game: Game,
+        font: BitFont,
+        bindings: Bindings,
+        record_path: Option<PathBuf>,
+    ) -> Self {
+        Self { game, font, bindings, record_path }
+    }
+}
+
+impl Scene for GameScene {
+    fn handle_input(&mut self, input: &Input) -> Transition {
+        if input.is_pressed(Action::Pause) {
+            return Transition::Push(Box::new(PauseScene::new(self.font.clone(), &self.bindings)));
+        }
+        Transition::None
+    }
+
+    fn update(&mut self, secs: f32) -> Transition {
+        self.game.update(secs);
+        if self.game.is_over() {
+            // No input is recorded while the game over screen is shown, so replays skip it and
+            // restart straight away, the same as when the screen was dismissed while recording
+            if self.game.is_replay() {
+                self.game.reset();
+                return Transition::None;
+            }
+            return Transition::Push(Box::new(GameOverScene::new(
+                self.font.clone(),
+                &self.bindings,
+            )));
+        }
+        Transition::None
+    }
+
+    fn draw(&mut self, view_size: Vec2, alpha: f32) {
+        self.game.draw(view_size, alpha);
+    }
+
+    fn resume(&mut self) {
+        // The game over screen was dismissed
+        if self.game.is_over() {
+            self.game.reset();
+        }
+    }
+
+    fn quit(&mut self) -> anyhow::Result<()> {
+        if let (Some(path), Some(recording)) = (&self.record_path, self.game.recording()) {
+            recording.save(path).context("Failed to save recording")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use macroquad::{
+    color::Color,
+    prelude::{Vec2, BLACK},
+    shapes::draw_rectangle,
+    window::clear_background,
+};
+
+use crate::{
+    bitfont::BitFont,
+    controller::input::{Action, Bindings, Input},
+    scene::{Scene, Transition},
+};
+
+/// The color drawn over the game behind overlays
+static SHADE: Color = Color::new(0.0, 0.0, 0.0, 0.6);
+
+/// The screen shown before the game starts. The game is below it on the stack, so popping this
+/// scene starts the game.
+pub struct TitleScene {
+    font: BitFont,
+    text: String,
+}
+
+impl TitleScene {
+    pub fn new(font: BitFont, bindings: &Bindings) -> Self {
+        let text = menu_text("PLATFORMER", bindings, &[
+            (Action::Confirm, "start"),
+            (Action::Quit, "quit"),
+        ]);
+        Self { font, text }
+    }
+}
+
+impl Scene for TitleScene {
+    fn handle_input(&mut self, input: &Input) -> Transition {
+        if input.is_pressed(Action::Confirm) {
+            Transition::Pop
+        }
+        else if input.is_pressed(Action::Quit) {
+            Transition::Quit
+        }
+        else {
+            Transition::None
+        }
+    }
+
+    fn draw(&mut self, view_size: Vec2, _: f32) {
+        clear_background(BLACK);
+        draw_centered_text(&self.font, view_size, &self.text);
+    }
+}
+
+/// Stops the game while it is drawn behind
+pub struct PauseScene {
+    font: BitFont,
+    text: String,
+}
+
+impl PauseScene {
+    pub fn new(font: BitFont, bindings: &Bindings) -> Self {
+        let text =
+            menu_text("PAUSED", bindings, &[(Action::Pause, "resume"), (Action::Quit, "quit")]);
+        Self { font, text }
+    }
+}
+
+impl Scene for PauseScene {
+    fn handle_input(&mut self, input: &Input) -> Transition {
+        if input.is_pressed(Action::Pause) {
+            Transition::Pop
+        }
+        else if input.is_pressed(Action::Quit) {
+            Transition::Quit
+        }
+        else {
+            Transition::None
+        }
+    }
+
+    fn draw(&mut self, view_size: Vec2, _: f32) {
+        draw_rectangle(0.0, 0.0, view_size.x, view_size.y, SHADE);
+        draw_centered_text(&self.font, view_size, &self.text);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// Shown over the game once the player is out of lives. Popping this scene restarts the game.
+pub struct GameOverScene {
+    font: BitFont,
+    text: String,
+}
+
+impl GameOverScene {
+    pub fn new(font: BitFont, bindings: &Bindings) -> Self {
+        let text = menu_text("GAME OVER", bindings, &[
+            (Action::Confirm, "try again"),
+            (Action::Quit, "quit"),
+        ]);
+        Self { font, text }
+    }
+}
+
+impl Scene for GameOverScene {
+    fn handle_input(&mut self, input: &Input) -> Transition {
+        if input.is_pressed(Action::Confirm) {
+            Transition::Pop
+        }
+        else if input.is_pressed(Action::Quit) {
+            Transition::Quit
+        }
+        else {
+            Transition::None
+        }
+    }
+
+    fn draw(&mut self, view_size: Vec2, _: f32) {
+        draw_rectangle(0.0, 0.0, view_size.x, view_size.y, SHADE);
+        draw_centered_text(&self.font, view_size, &self.text);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// Draws text in the middle of the screen
+fn draw_centered_text(font: &BitFont, view_size: Vec2, text: &str) {
+    let position = ((view_size - font.text_size(text)) / 2.0).round();
+    font.draw_text(position, text);
+}
+
+/// Builds the text of a menu, telling the player which controls to use
+/// # Arguments
+/// `title` - The heading of the menu
+/// `bindings` - The controls bound to each action
+/// `choices` - The actions the menu responds to, and what they do
+fn menu_text(title: &str, bindings: &Bindings, choices: &[(Action, &str)]) -> String {
+    let mut text = format!("{title}\n");
+    for &(action, what) in choices {
+        text += &format!("\nPress {} to {what}", bindings.describe(action));
+    }
+    text
+}
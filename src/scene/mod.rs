@@ -0,0 +1,234 @@
+use macroquad::prelude::Vec2;
+
+use crate::controller::input::Input;
+
+pub mod gameplay;
+pub mod menu;
+
+/// What the scene stack should do after a scene handles input or updates
+pub enum Transition {
+    /// Keep the current scene
+    None,
+    /// Put a new scene on top of the current one
+    Push(Box<dyn Scene>),
+    /// Remove the current scene, returning to the one below it
+    Pop,
+    /// Remove every scene, which ends the game
+    Quit,
+}
+
+/// A screen of the game, such as the title screen or the gameplay itself. Only the scene on top of
+/// the stack handles input and is updated, but overlays let the scenes below them be drawn.
+pub trait Scene {
+    /// Handles the input for this frame
+    /// # Arguments
+    /// `input` - The actions the player is doing
+    /// # Return
+    /// Returns the transition to make
+    fn handle_input(&mut self, _input: &Input) -> Transition {
+        Transition::None
+    }
+
+    /// Updates the scene
+    /// # Arguments
+    /// `secs` - The total seconds elapsed since the last update
+    /// # Return
+    /// Returns the transition to make
+    fn update(&mut self, _secs: f32) -> Transition {
+        Transition::None
+    }
+
+    /// Draws the scene
+    /// # Arguments
+    /// `view_size` - The size of the screen being drawn to
+    /// `alpha` - How far the simulation is between the previous and next update (0.0 to 1.0)
+    fn draw(&mut self, view_size: Vec2, alpha: f32);
+
+    /// Checks if the scene is drawn over the scene below it
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Called when the scene is back on top of the stack after the scene above it was popped
+    fn resume(&mut self) {}
+
+    /// Called on every scene in the stack before the game ends
+    fn quit(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// A stack of scenes, where the top scene is the one being played
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl SceneStack {
+    /// Creates a scene stack
+    /// # Arguments
+    /// `scene` - The scene at the bottom of the stack
+    pub fn new(scene: Box<dyn Scene>) -> Self {
+        Self { scenes: vec![scene] }
+    }
+
+    /// Puts a new scene on top of the stack
+    pub fn push(&mut self, scene: Box<dyn Scene>) {
+        self.scenes.push(scene);
+    }
+
+    /// Checks if every scene has been removed, meaning the game should end
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// Lets the top scene handle the input for this frame
+    /// # Arguments
+    /// `input` - The actions the player is doing
+    pub fn handle_input(&mut self, input: &Input) -> anyhow::Result<()> {
+        match self.scenes.last_mut() {
+            Some(scene) => {
+                let transition = scene.handle_input(input);
+                self.apply(transition)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Updates the top scene
+    /// # Arguments
+    /// `secs` - The total seconds elapsed since the last update
+    pub fn update(&mut self, secs: f32) -> anyhow::Result<()> {
+        match self.scenes.last_mut() {
+            Some(scene) => {
+                let transition = scene.update(secs);
+                self.apply(transition)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Draws the top scene, along with the scenes below it that it is drawn over
+    /// # Arguments
+    /// `view_size` - The size of the screen being drawn to
+    /// `alpha` - How far the simulation is between the previous and next update (0.0 to 1.0)
+    pub fn draw(&mut self, view_size: Vec2, alpha: f32) {
+        let bottom = self.scenes.iter().rposition(|scene| !scene.is_overlay()).unwrap_or(0);
+        for scene in &mut self.scenes[bottom..] {
+            scene.draw(view_size, alpha);
+        }
+    }
+
+    /// Removes every scene, letting each one finish up first
+    pub fn quit(&mut self) -> anyhow::Result<()> {
+        while let Some(mut scene) = self.scenes.pop() {
+            scene.quit()?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, transition: Transition) -> anyhow::Result<()> {
+        match transition {
+            Transition::None => {}
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                self.scenes.pop();
+                if let Some(scene) = self.scenes.last_mut() {
+                    scene.resume();
+                }
+            }
+            Transition::Quit => self.quit()?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    /// A scene that records what happens to it, and makes a transition when it is updated
+    struct TestScene {
+        name: &'static str,
+        overlay: bool,
+        events: Rc<RefCell<Vec<String>>>,
+        next: Option<Transition>,
+    }
+
+    impl TestScene {
+        fn new(name: &'static str, overlay: bool, events: &Rc<RefCell<Vec<String>>>) -> Box<Self> {
+            Box::new(Self { name, overlay, events: events.clone(), next: None })
+        }
+
+        fn then(mut self: Box<Self>, transition: Transition) -> Box<Self> {
+            self.next = Some(transition);
+            self
+        }
+
+        fn log(&self, event: &str) {
+            self.events.borrow_mut().push(format!("{} {event}", self.name));
+        }
+    }
+
+    impl Scene for TestScene {
+        fn update(&mut self, _: f32) -> Transition {
+            self.log("update");
+            self.next.take().unwrap_or(Transition::None)
+        }
+
+        fn draw(&mut self, _: Vec2, _: f32) {
+            self.log("draw");
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+
+        fn resume(&mut self) {
+            self.log("resume");
+        }
+
+        fn quit(&mut self) -> anyhow::Result<()> {
+            self.log("quit");
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn overlays_draw_over_the_scenes_below() {
+        let events = Rc::new(RefCell::new(vec![]));
+        let mut scenes = SceneStack::new(TestScene::new("game", false, &events));
+        scenes.push(TestScene::new("title", false, &events));
+        scenes.push(TestScene::new("pause", true, &events));
+
+        scenes.update(1.0).unwrap();
+        scenes.draw(Vec2::ONE, 0.0);
+        assert_eq!(*events.borrow(), ["pause update", "title draw", "pause draw"]);
+    }
+
+    #[test]
+    fn popping_resumes_the_scene_below() {
+        let events = Rc::new(RefCell::new(vec![]));
+        let mut scenes = SceneStack::new(TestScene::new("game", false, &events));
+        let pause = TestScene::new("pause", true, &events);
+        scenes.push(TestScene::new("title", false, &events).then(Transition::Push(pause)));
+
+        scenes.update(1.0).unwrap();
+        scenes.update(1.0).unwrap();
+        scenes.apply(Transition::Pop).unwrap();
+        scenes.apply(Transition::Pop).unwrap();
+        scenes.update(1.0).unwrap();
+        assert_eq!(*events.borrow(), [
+            "title update",
+            "pause update",
+            "title resume",
+            "game resume",
+            "game update"
+        ]);
+
+        scenes.apply(Transition::Quit).unwrap();
+        assert!(scenes.is_empty());
+        assert_eq!(events.borrow().last().unwrap(), "game quit");
+    }
+}
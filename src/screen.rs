@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+use macroquad::{
+    camera::{set_camera, set_default_camera, Camera2D},
+    input::mouse_position,
+    prelude::{Vec2, BLACK, WHITE},
+    texture::{draw_texture_ex, render_target, DrawTextureParams, FilterMode, RenderTarget},
+    window::{clear_background, screen_height, screen_width},
+};
+
+/// How the virtual screen is scaled up to fit the window
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ScaleMode {
+    /// Scale by a whole number, so every virtual pixel is the same size
+    Integer,
+    /// Scale by as much as fits, keeping the aspect ratio
+    Fit,
+}
+
+impl FromStr for ScaleMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "integer" => Ok(ScaleMode::Integer),
+            "fit" => Ok(ScaleMode::Fit),
+            _ => anyhow::bail!("Unknown scale mode '{s}', expected 'integer' or 'fit'"),
+        }
+    }
+}
+
+/// Where the virtual screen is drawn in the window. Any space around it is left as black bars.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Letterbox {
+    /// The size of a virtual pixel in window pixels
+    pub scale: f32,
+    /// The position of the top left corner of the virtual screen in the window
+    pub offset: Vec2,
+}
+
+impl Letterbox {
+    /// Fits a virtual screen inside a window
+    /// # Arguments
+    /// `virtual_size` - The size of the virtual screen
+    /// `window_size` - The size of the window
+    /// `mode` - How to scale the virtual screen
+    pub fn new(virtual_size: Vec2, window_size: Vec2, mode: ScaleMode) -> Self {
+        let fit = (window_size / virtual_size).min_element();
+        let scale = match mode {
+            // Windows smaller than the virtual screen have to be scaled down to fit
+            ScaleMode::Integer if fit >= 1.0 => fit.floor(),
+            ScaleMode::Integer | ScaleMode::Fit => fit,
+        };
+        let offset = ((window_size - virtual_size * scale) / 2.0).floor();
+        Self { scale, offset }
+    }
+
+    /// Converts a point in the window to a point on the virtual screen
+    pub fn to_virtual(&self, point: Vec2) -> Vec2 {
+        (point - self.offset) / self.scale
+    }
+
+    /// Converts a point on the virtual screen to a point in the window
+    pub fn to_window(&self, point: Vec2) -> Vec2 {
+        point * self.scale + self.offset
+    }
+}
+
+/// An offscreen render target with a fixed resolution, which is scaled up to fit the window. The
+/// game looks the same no matter the size of the window.
+pub struct VirtualScreen {
+    size: Vec2,
+    mode: ScaleMode,
+    target: RenderTarget,
+}
+
+impl VirtualScreen {
+    /// Creates a new virtual screen
+    /// # Arguments
+    /// `width` - The width of the virtual screen in pixels
+    /// `height` - The height of the virtual screen in pixels
+    /// `mode` - How to scale the virtual screen to fit the window
+    pub fn new(width: u32, height: u32, mode: ScaleMode) -> Self {
+        let target = render_target(width, height);
+        target.texture.set_filter(FilterMode::Nearest);
+        Self { size: Vec2::new(width as f32, height as f32), mode, target }
+    }
+
+    /// Gets the size of the virtual screen in pixels
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    /// Gets where the virtual screen is drawn in the window
+    pub fn letterbox(&self) -> Letterbox {
+        Letterbox::new(self.size, Vec2::new(screen_width(), screen_height()), self.mode)
+    }
+
+    /// Gets the position of the mouse on the virtual screen
+    pub fn mouse_position(&self) -> Vec2 {
+        self.letterbox().to_virtual(mouse_position().into())
+    }
+
+    /// Starts drawing to the virtual screen. Everything drawn until `end` is called uses virtual
+    /// pixels, with the origin at the top left.
+    pub fn begin(&self) {
+        set_camera(&Camera2D {
+            // Render targets are flipped vertically compared to the window, so y is not negated
+            zoom: Vec2::new(2.0 / self.size.x, 2.0 / self.size.y),
+            target: self.size / 2.0,
+            render_target: Some(self.target),
+            ..Default::default()
+        });
+    }
+
+    /// Finishes drawing to the virtual screen and draws it scaled up in the window
+    pub fn end(&self) {
+        set_default_camera();
+        clear_background(BLACK);
+        let letterbox = self.letterbox();
+        draw_texture_ex(
+            self.target.texture,
+            letterbox.offset.x,
+            letterbox.offset.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(self.size * letterbox.scale),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static VIRTUAL: Vec2 = Vec2::new(320.0, 180.0);
+
+    #[test]
+    fn integer_scaling_keeps_pixels_square() {
+        let letterbox = Letterbox::new(VIRTUAL, Vec2::new(1000.0, 600.0), ScaleMode::Integer);
+        assert_eq!(letterbox, Letterbox { scale: 3.0, offset: Vec2::new(20.0, 30.0) });
+
+        // Windows smaller than the virtual screen are scaled down instead of cutting it off
+        let letterbox = Letterbox::new(VIRTUAL, Vec2::new(160.0, 180.0), ScaleMode::Integer);
+        assert_eq!(letterbox, Letterbox { scale: 0.5, offset: Vec2::new(0.0, 45.0) });
+    }
+
+    #[test]
+    fn fit_scaling_keeps_aspect_ratio() {
+        let letterbox = Letterbox::new(VIRTUAL, Vec2::new(1000.0, 600.0), ScaleMode::Fit);
+        assert_eq!(letterbox.scale, 3.125);
+        assert_eq!(letterbox.offset, Vec2::new(0.0, 18.0));
+    }
+
+    #[test]
+    fn converts_between_window_and_virtual_points() {
+        let letterbox = Letterbox::new(VIRTUAL, Vec2::new(1000.0, 600.0), ScaleMode::Integer);
+        assert_eq!(letterbox.to_virtual(Vec2::new(20.0, 30.0)), Vec2::ZERO);
+        assert_eq!(letterbox.to_virtual(Vec2::new(980.0, 570.0)), VIRTUAL);
+        let point = Vec2::new(100.0, 50.0);
+        assert_eq!(letterbox.to_virtual(letterbox.to_window(point)), point);
+    }
+}
@@ -1,14 +1,22 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
 use macroquad::{
     prelude::{Rect, UVec2, Vec2, WHITE},
     texture::{draw_texture_ex, DrawTextureParams, Texture2D},
 };
+use serde::Deserialize;
 
 pub static NEXT_ANIMATION_ID: std::sync::atomic::AtomicUsize =
     std::sync::atomic::AtomicUsize::new(0);
 
+/// The layout of a set of frames within a spritesheet. The spritesheet itself is supplied when
+/// drawing, so sprites can be created and animated without a graphics context.
 #[derive(Clone)]
 pub struct Sprite {
-    pub spritesheet: Texture2D,
     pub offset: UVec2,
     pub frame_width: u32,
     pub frame_height: u32,
@@ -17,7 +25,7 @@ pub struct Sprite {
 }
 
 impl Sprite {
-    pub fn draw(&self, frame: UVec2, pos: Vec2, flip_x: bool) {
+    pub fn draw(&self, spritesheet: Texture2D, frame: UVec2, pos: Vec2, flip_x: bool) {
         assert!(frame.x < self.num_frames_x);
         assert!(frame.y < self.num_frames_y);
 
@@ -31,7 +39,7 @@ impl Sprite {
         let pos = pos.round();
         let dest_rect = Rect::new(pos.x, pos.y, self.frame_width as f32, self.frame_height as f32);
 
-        draw_texture_ex(self.spritesheet, dest_rect.x, dest_rect.y, WHITE, DrawTextureParams {
+        draw_texture_ex(spritesheet, dest_rect.x, dest_rect.y, WHITE, DrawTextureParams {
             dest_size: Some(dest_rect.size()),
             source: Some(source_rect),
             flip_x,
@@ -44,10 +52,206 @@ impl Sprite {
 pub struct Animation {
     pub id: usize,
     pub sprite: Sprite,
-    pub frame_time: f32,
+    /// The number of seconds to show each frame for, one per frame. A frame with a time of 0.0 is
+    /// shown until a different animation is played.
+    pub frame_times: Vec<f32>,
     pub repeat: bool,
 }
 
+impl Animation {
+    /// Creates a new animation with a unique id
+    /// # Arguments
+    /// `sprite` - The frames of the animation
+    /// `frame_times` - The number of seconds to show each frame for, one per frame
+    /// `repeat` - Whether the animation starts again after the last frame
+    pub fn new(sprite: Sprite, frame_times: Vec<f32>, repeat: bool) -> Self {
+        let id = NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self { id, sprite, frame_times, repeat }
+    }
+
+    /// Gets the number of seconds to show a frame for
+    fn frame_time(&self, frame: u32) -> f32 {
+        self.frame_times.get(frame as usize).copied().unwrap_or(0.0)
+    }
+}
+
+/// A set of named animations that share a spritesheet, loaded from an animation set file. See
+/// `docs/README.md` for a description of the format.
+pub struct AnimationSet {
+    /// The path to the spritesheet
+    pub sheet: PathBuf,
+    /// The file the set was loaded from, used in error messages
+    source: PathBuf,
+    clips: BTreeMap<String, Animation>,
+}
+
+/// The layout of an animation set file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnimationSetFile {
+    sheet: PathBuf,
+    frame_width: u32,
+    frame_height: u32,
+    clips: BTreeMap<String, ClipFile>,
+}
+
+/// The layout of an animation in an animation set file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClipFile {
+    #[serde(default)]
+    row: u32,
+    /// The first and last column of the animation's frames
+    frames: [u32; 2],
+    #[serde(default)]
+    frame_time: FrameTimes,
+    #[serde(default)]
+    mode: LoopMode,
+}
+
+/// How long frames are shown for, either the same time for every frame or one time per frame
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrameTimes {
+    All(f32),
+    PerFrame(Vec<f32>),
+}
+
+impl Default for FrameTimes {
+    fn default() -> Self {
+        FrameTimes::All(0.0)
+    }
+}
+
+/// What an animation does after its last frame
+#[derive(Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LoopMode {
+    /// Start again from the first frame
+    #[default]
+    Loop,
+    /// Stay on the last frame
+    Once,
+}
+
+impl AnimationSet {
+    /// Loads an animation set from a file
+    /// # Arguments
+    /// `path` - The path to the animation set file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to open animation set: {}", path.display()))?;
+        Self::parse(&text, path)
+            .with_context(|| format!("Invalid animation set: {}", path.display()))
+    }
+
+    /// Parses the contents of an animation set file
+    /// # Arguments
+    /// `text` - The contents of the file
+    /// `path` - The path to the file, which the spritesheet path is relative to
+    fn parse(text: &str, path: &Path) -> anyhow::Result<Self> {
+        let file: AnimationSetFile = serde_json::from_str(text)?;
+        if file.frame_width == 0 || file.frame_height == 0 {
+            anyhow::bail!("Invalid frame size: {}x{}", file.frame_width, file.frame_height);
+        }
+
+        let mut clips = BTreeMap::new();
+        for (name, clip) in file.clips {
+            let [first, last] = clip.frames;
+            if first > last {
+                anyhow::bail!(
+                    "Animation '{name}' ends at frame {last} before it starts at {first}"
+                );
+            }
+            let num_frames = last - first + 1;
+            let frame_times = match clip.frame_time {
+                FrameTimes::All(time) => vec![time; num_frames as usize],
+                FrameTimes::PerFrame(times) if times.len() == num_frames as usize => times,
+                FrameTimes::PerFrame(times) => anyhow::bail!(
+                    "Animation '{name}' has {num_frames} frames, but {} frame times",
+                    times.len()
+                ),
+            };
+            if frame_times.iter().any(|&time| time < 0.0) {
+                anyhow::bail!("Animation '{name}' has a negative frame time");
+            }
+
+            let sprite = Sprite {
+                offset: UVec2::new(first * file.frame_width, clip.row * file.frame_height),
+                frame_width: file.frame_width,
+                frame_height: file.frame_height,
+                num_frames_x: num_frames,
+                num_frames_y: 1,
+            };
+            let repeat = matches!(clip.mode, LoopMode::Loop);
+            clips.insert(name, Animation::new(sprite, frame_times, repeat));
+        }
+
+        // The spritesheet path is relative to the animation set
+        let sheet = path.parent().unwrap_or(Path::new("")).join(file.sheet);
+        Ok(Self { sheet, source: path.to_owned(), clips })
+    }
+
+    /// Gets an animation from the set
+    /// # Arguments
+    /// `name` - The name of the animation
+    /// # Return
+    /// Returns the animation, or an error if the set doesn't have it
+    pub fn clip(&self, name: &str) -> anyhow::Result<Animation> {
+        self.optional_clip(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} has no '{name}' animation, it only has: {}",
+                self.source.display(),
+                self.names().join(", ")
+            )
+        })
+    }
+
+    /// Gets an animation from the set, or `None` if the set doesn't have it
+    pub fn optional_clip(&self, name: &str) -> Option<Animation> {
+        self.clips.get(name).cloned()
+    }
+
+    /// Checks that every animation in the set is one of the animations the user of the set knows
+    /// about, which catches misspelled names
+    /// # Arguments
+    /// `known` - The names of the animations that are used
+    pub fn check_names(&self, known: &[&str]) -> anyhow::Result<()> {
+        match self.names().into_iter().find(|name| !known.contains(name)) {
+            Some(name) => anyhow::bail!(
+                "{} has an unknown animation '{name}', expected one of: {}",
+                self.source.display(),
+                known.join(", ")
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that every frame of every animation is inside the spritesheet
+    /// # Arguments
+    /// `width` - The width of the spritesheet in pixels
+    /// `height` - The height of the spritesheet in pixels
+    pub fn check_texture_size(&self, width: u32, height: u32) -> anyhow::Result<()> {
+        for (name, clip) in &self.clips {
+            let sprite = &clip.sprite;
+            let right = sprite.offset.x + sprite.num_frames_x * sprite.frame_width;
+            let bottom = sprite.offset.y + sprite.num_frames_y * sprite.frame_height;
+            if right > width || bottom > height {
+                anyhow::bail!(
+                    "Animation '{name}' in {} goes past the edge of {}, which is {width}x{height}",
+                    self.source.display(),
+                    self.sheet.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.clips.keys().map(|name| name.as_str()).collect()
+    }
+}
+
 pub struct AnimationPlayer {
     pub speed_up: f32,
     animation: Animation,
@@ -88,27 +292,110 @@ impl AnimationPlayer {
     }
 
     pub fn update(&mut self, secs: f32) {
-        if !self.stopped && self.animation.frame_time != 0.0 {
-            self.wait_time += secs;
-            while self.wait_time > self.animation.frame_time * self.speed_up {
-                self.wait_time -= self.animation.frame_time * self.speed_up;
-                self.frame.x = if self.frame.x + 1 < self.animation.sprite.num_frames_x {
-                    self.frame.x + 1
-                }
-                else {
-                    if !self.animation.repeat {
-                        self.stopped = true;
-                        break;
-                    }
-                    else {
-                        0
-                    }
-                };
+        if self.stopped || self.animation.frame_time(self.frame.x) == 0.0 {
+            return;
+        }
+        self.wait_time += secs;
+        loop {
+            let frame_time = self.animation.frame_time(self.frame.x) * self.speed_up;
+            if frame_time == 0.0 || self.wait_time <= frame_time {
+                break;
+            }
+            self.wait_time -= frame_time;
+            if self.frame.x + 1 < self.animation.sprite.num_frames_x {
+                self.frame.x += 1;
+            }
+            else if self.animation.repeat {
+                self.frame.x = 0;
+            }
+            else {
+                self.stopped = true;
+                break;
             }
         }
     }
 
-    pub fn draw(&self, pos: Vec2) {
-        self.animation.sprite.draw(self.frame, pos, self.flip_x);
+    pub fn draw(&self, spritesheet: Texture2D, pos: Vec2) {
+        self.animation.sprite.draw(spritesheet, self.frame, pos, self.flip_x);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SET: &str = r#"{
+        "sheet": "creature.png",
+        "frame_width": 32,
+        "frame_height": 64,
+        "clips": {
+            "idle": { "frames": [0, 0] },
+            "walk": { "row": 1, "frames": [2, 4], "frame_time": 0.5 },
+            "land": { "row": 2, "frames": [0, 1], "frame_time": [0.1, 0.3], "mode": "once" }
+        }
+    }"#;
+
+    fn load(text: &str) -> anyhow::Result<AnimationSet> {
+        AnimationSet::parse(text, Path::new("assets/creature.json"))
+    }
+
+    #[test]
+    fn loads_animation_sets() {
+        let set = load(SET).unwrap();
+        assert_eq!(set.sheet, Path::new("assets/creature.png"));
+
+        let walk = set.clip("walk").unwrap();
+        assert_eq!(walk.sprite.offset, UVec2::new(64, 64));
+        assert_eq!(walk.sprite.num_frames_x, 3);
+        assert_eq!(walk.frame_times, vec![0.5; 3]);
+        assert!(walk.repeat);
+
+        let land = set.clip("land").unwrap();
+        assert_eq!(land.frame_times, vec![0.1, 0.3]);
+        assert!(!land.repeat);
+        assert_ne!(land.id, walk.id);
+
+        assert!(set.check_texture_size(160, 192).is_ok());
+        assert!(set.check_texture_size(128, 192).is_err());
+    }
+
+    #[test]
+    fn reports_unknown_animations() {
+        let set = load(SET).unwrap();
+        let error = set.clip("jump").err().unwrap().to_string();
+        assert_eq!(
+            error,
+            "assets/creature.json has no 'jump' animation, it only has: idle, land, walk"
+        );
+        let error = set.check_names(&["idle", "walk"]).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "assets/creature.json has an unknown animation 'land', expected one of: idle, walk"
+        );
+        assert!(set.check_names(&["idle", "land", "walk"]).is_ok());
+
+        let mismatched = SET.replace("[0.1, 0.3]", "[0.1]");
+        assert!(load(&mismatched).is_err());
+    }
+
+    #[test]
+    fn plays_frames_for_their_own_time() {
+        let sprite = Sprite {
+            offset: UVec2::ZERO,
+            frame_width: 32,
+            frame_height: 32,
+            num_frames_x: 3,
+            num_frames_y: 1,
+        };
+        let mut player = AnimationPlayer::new(Animation::new(sprite, vec![0.1, 0.3, 0.1], false));
+        player.update(0.15);
+        assert_eq!(player.frame.x, 1);
+        player.update(0.2);
+        assert_eq!(player.frame.x, 1);
+        player.update(0.2);
+        assert_eq!(player.frame.x, 2);
+        // Animations that don't repeat stop on their last frame
+        player.update(1.0);
+        assert_eq!(player.frame.x, 2);
     }
 }
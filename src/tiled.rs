@@ -0,0 +1,1049 @@
+//! Importer for maps made with the Tiled editor (https://www.mapeditor.org), supporting both the
+//! JSON (.tmj) and XML (.tmx) formats.
+//!
+//! Tile layers become map layers: a layer's `kind` property (or failing that, its name) decides
+//! whether it is a background, collision or foreground layer. Tile properties `collision` (or the
+//! older `solid`) and `friction` become the map's `TileInfo`. Objects become object spawns using
+//! the object's class, apart from an object with the class `player`, which sets the player's spawn
+//! point. Image layers become parallax layers, which are always drawn behind the tile layers.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use macroquad::prelude::Vec2;
+use serde::Deserialize;
+
+use crate::{
+    map::{LayerKind, Map, ObjectSpawn, ParallaxLayer, TileLayer, EMPTY_TILE},
+    tiles::{TileAnimation, TileCollision, TileInfo, TileSet},
+};
+
+/// The id that the first tile of a Tiled tileset becomes. Tiled numbers tiles from 0, but id 0 is
+/// kept for empty space.
+static FIRST_ID: u16 = EMPTY_TILE + 1;
+
+/// The flags Tiled stores in the top bits of a tile id for flipped and rotated tiles
+static GID_FLAGS: u32 = 0xf000_0000;
+
+/// Checks if a path looks like a Tiled map
+pub fn is_tiled_map(path: &Path) -> bool {
+    matches!(path.extension().and_then(|ext| ext.to_str()), Some("tmx" | "tmj"))
+}
+
+/// Loads a map made with Tiled
+pub fn load_map(path: &Path) -> anyhow::Result<Map> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to open: {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let tiled = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("tmj") => parse_json_map(&text, dir),
+        Some("tmx") => parse_xml_map(&text, dir),
+        _ => anyhow::bail!("Unknown Tiled map extension, expected .tmj or .tmx"),
+    }
+    .with_context(|| format!("failed to load Tiled map: {}", path.display()))?;
+
+    let default_name = path.file_stem().map(|name| name.to_string_lossy().into_owned());
+    convert_map(tiled, default_name.unwrap_or_default())
+        .with_context(|| format!("failed to import Tiled map: {}", path.display()))
+}
+
+/// A Tiled map, independent of the format it was loaded from
+struct TiledMap {
+    orientation: String,
+    infinite: bool,
+    width: usize,
+    height: usize,
+    tile_width: u32,
+    tile_height: u32,
+    properties: Properties,
+    tilesets: Vec<TiledTileset>,
+    layers: Vec<TiledLayer>,
+}
+
+struct TiledTileset {
+    first_gid: u32,
+    tile_width: u32,
+    tile_height: u32,
+    tile_count: u32,
+    margin: i32,
+    spacing: i32,
+    /// The path to the tileset image, relative to the working directory
+    image: Option<PathBuf>,
+    tiles: Vec<(u32, Properties)>,
+    /// The animated tiles, each with a list of frames as (tile id, duration in milliseconds)
+    animations: Vec<(u32, Vec<(u32, u32)>)>,
+}
+
+enum TiledLayer {
+    Tiles {
+        name: String,
+        properties: Properties,
+        data: Vec<u32>,
+    },
+    Objects {
+        objects: Vec<TiledObject>,
+    },
+    /// An image layer, or `None` if the layer has no image
+    Image(Option<ParallaxLayer>),
+    Unsupported {
+        name: String,
+        kind: &'static str,
+    },
+}
+
+struct TiledObject {
+    class: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    gid: Option<u32>,
+    properties: Properties,
+}
+
+/// Custom properties, with all values converted to strings
+type Properties = BTreeMap<String, String>;
+
+fn convert_map(tiled: TiledMap, default_name: String) -> anyhow::Result<Map> {
+    if tiled.orientation != "orthogonal" {
+        anyhow::bail!(
+            "Unsupported map orientation: {}, only orthogonal maps are supported",
+            tiled.orientation
+        );
+    }
+    if tiled.infinite {
+        anyhow::bail!("Infinite maps are not supported, disable 'Infinite' in the map properties");
+    }
+    if tiled.tile_width != tiled.tile_height {
+        anyhow::bail!("Tiles must be square, but are {}x{}", tiled.tile_width, tiled.tile_height);
+    }
+
+    let tileset = match tiled.tilesets.as_slice() {
+        [tileset] => tileset,
+        [] => anyhow::bail!("Map has no tileset"),
+        _ => {
+            anyhow::bail!("Maps can only use a single tileset, but found {}", tiled.tilesets.len())
+        }
+    };
+    if (tileset.tile_width, tileset.tile_height) != (tiled.tile_width, tiled.tile_height) {
+        anyhow::bail!(
+            "Tileset tiles are {}x{}, but the map uses {}x{} tiles",
+            tileset.tile_width,
+            tileset.tile_height,
+            tiled.tile_width,
+            tiled.tile_height
+        );
+    }
+
+    let tile_info = convert_tile_info(tileset)?;
+
+    let num_tile_layers =
+        tiled.layers.iter().filter(|layer| matches!(layer, TiledLayer::Tiles { .. })).count();
+    let mut layers = vec![];
+    let mut objects = vec![];
+    let mut parallax = vec![];
+    let mut spawn = None;
+    for layer in tiled.layers {
+        match layer {
+            TiledLayer::Tiles { name, properties, data } => {
+                let kind = layer_kind(&name, &properties, num_tile_layers)?;
+                if data.len() != tiled.width * tiled.height {
+                    anyhow::bail!(
+                        "Layer '{name}' has {} tiles, but the map is {}x{}",
+                        data.len(),
+                        tiled.width,
+                        tiled.height
+                    );
+                }
+                let tiles = data
+                    .iter()
+                    .map(|&gid| convert_gid(gid, tileset))
+                    .collect::<anyhow::Result<_>>()
+                    .with_context(|| format!("Invalid tile in layer '{name}'"))?;
+                layers.push(TileLayer { kind, tiles });
+            }
+            TiledLayer::Objects { objects: layer_objects } => {
+                for object in layer_objects {
+                    let mut position = Vec2::new(object.x, object.y);
+                    // Tile objects are positioned by their bottom left corner
+                    if object.gid.is_some() {
+                        position.y -= object.height;
+                    }
+                    match object.class.as_str() {
+                        // Objects without a class are left for notes and decoration in the editor
+                        "" => {}
+                        "player" => spawn = Some(position),
+                        "checkpoint" => {
+                            // Checkpoints use the size of the object unless it is overridden or
+                            // the object is a point
+                            let mut properties = object.properties;
+                            if object.width > 0.0 && object.height > 0.0 {
+                                properties
+                                    .entry("width".into())
+                                    .or_insert(object.width.to_string());
+                                properties
+                                    .entry("height".into())
+                                    .or_insert(object.height.to_string());
+                            }
+                            objects.push(ObjectSpawn { kind: object.class, position, properties })
+                        }
+                        _ => objects.push(ObjectSpawn {
+                            kind: object.class,
+                            position,
+                            properties: object.properties,
+                        }),
+                    }
+                }
+            }
+            TiledLayer::Image(layer) => parallax.extend(layer),
+            TiledLayer::Unsupported { name, kind } => {
+                anyhow::bail!("Layer '{name}' is an {kind}, which is not supported")
+            }
+        }
+    }
+
+    let image = tileset.image.clone().ok_or_else(|| {
+        anyhow::anyhow!("Tilesets made from a collection of images are not supported")
+    })?;
+    let tileset_info = TileSet {
+        tile_size: tiled.tile_width as i32,
+        margin: tileset.margin,
+        spacing: tileset.spacing,
+        texture: image,
+        first_id: FIRST_ID,
+        tile_info,
+        animations: convert_animations(tileset)?,
+    };
+    let mut map = Map::with_layers(tiled.width, tiled.height, layers, tileset_info)?;
+    map.name = tiled.properties.get("name").cloned().unwrap_or(default_name);
+    map.spawn = spawn;
+    map.objects = objects;
+    map.parallax = parallax;
+    Ok(map)
+}
+
+/// Builds the tile info for every tile in the tileset from the tile properties, with an empty tile
+/// before them
+fn convert_tile_info(tileset: &TiledTileset) -> anyhow::Result<Vec<TileInfo>> {
+    if tileset.tile_count >= (u16::MAX - FIRST_ID) as u32 {
+        anyhow::bail!("Tileset has {} tiles, which is too many", tileset.tile_count);
+    }
+    let mut tile_info = vec![
+        TileInfo { collision: TileCollision::None, friction: 1.0 };
+        (FIRST_ID as u32 + tileset.tile_count) as usize
+    ];
+    for (id, properties) in &tileset.tiles {
+        let info = tile_info
+            .get_mut((FIRST_ID as u32 + id) as usize)
+            .filter(|_| *id < tileset.tile_count)
+            .ok_or_else(|| anyhow::anyhow!("Tileset has properties for missing tile {id}"))?;
+        if let Some(collision) = properties.get("collision") {
+            info.collision = collision
+                .parse()
+                .with_context(|| format!("Invalid 'collision' for tile {id}: {collision}"))?;
+        }
+        // Older tilesets mark solid tiles with a bool, which `collision` takes priority over
+        else if let Some(solid) = properties.get("solid") {
+            let solid: bool =
+                solid.parse().with_context(|| format!("Invalid 'solid' for tile {id}: {solid}"))?;
+            info.collision = if solid { TileCollision::Solid } else { TileCollision::None };
+        }
+        if let Some(friction) = properties.get("friction") {
+            info.friction = friction
+                .parse()
+                .with_context(|| format!("Invalid 'friction' for tile {id}: {friction}"))?;
+        }
+    }
+    Ok(tile_info)
+}
+
+/// Converts the tile animations of a tileset. Every frame of an animation must have the same
+/// duration.
+fn convert_animations(tileset: &TiledTileset) -> anyhow::Result<HashMap<u16, TileAnimation>> {
+    let mut animations = HashMap::new();
+    for (id, frames) in &tileset.animations {
+        let duration = match frames.as_slice() {
+            [] => continue,
+            [(_, duration), rest @ ..] if rest.iter().all(|frame| frame.1 == *duration) => {
+                *duration
+            }
+            _ => anyhow::bail!(
+                "Frames of the animation of tile {id} must all have the same duration"
+            ),
+        };
+        if duration == 0 {
+            anyhow::bail!("Frames of the animation of tile {id} must have a positive duration");
+        }
+        if *id >= tileset.tile_count || frames.iter().any(|&(frame, _)| frame >= tileset.tile_count)
+        {
+            anyhow::bail!("Animation of tile {id} uses a tile that is not part of the tileset");
+        }
+        let frames = frames.iter().map(|&(frame, _)| frame as u16 + FIRST_ID).collect();
+        let frame_time = duration as f32 / 1000.0;
+        animations.insert(*id as u16 + FIRST_ID, TileAnimation { frames, frame_time });
+    }
+    Ok(animations)
+}
+
+/// Converts a Tiled global tile id to a tile id in the map's tileset
+fn convert_gid(gid: u32, tileset: &TiledTileset) -> anyhow::Result<u16> {
+    if gid == 0 {
+        return Ok(EMPTY_TILE);
+    }
+    if gid & GID_FLAGS != 0 {
+        anyhow::bail!("Flipped and rotated tiles are not supported");
+    }
+    match gid.checked_sub(tileset.first_gid) {
+        Some(id) if id < tileset.tile_count => Ok(id as u16 + FIRST_ID),
+        _ => anyhow::bail!("Tile id {gid} is not part of the tileset"),
+    }
+}
+
+/// Works out which kind of layer a Tiled tile layer should become
+fn layer_kind(
+    name: &str,
+    properties: &Properties,
+    num_tile_layers: usize,
+) -> anyhow::Result<LayerKind> {
+    let kind = properties.get("kind").map(|kind| kind.as_str()).unwrap_or(name);
+    match kind.to_lowercase().as_str() {
+        "background" => Ok(LayerKind::Background),
+        "collision" => Ok(LayerKind::Collision),
+        "foreground" => Ok(LayerKind::Foreground),
+        // A lone tile layer has to be the collision layer
+        _ if num_tile_layers == 1 => Ok(LayerKind::Collision),
+        _ => anyhow::bail!(
+            "Could not tell what kind of layer '{name}' is. Name the layer 'background', \
+             'collision' or 'foreground', or give it a 'kind' property with one of those values"
+        ),
+    }
+}
+
+/// Decodes the tile ids stored in an encoded layer
+fn decode_layer_data(
+    data: &str,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+) -> anyhow::Result<Vec<u32>> {
+    if let Some(compression) = compression.filter(|compression| !compression.is_empty()) {
+        anyhow::bail!(
+            "Compressed layers ({compression}) are not supported, set the tile layer format to CSV"
+        );
+    }
+    match encoding {
+        Some("csv") => data
+            .split(',')
+            .map(|gid| {
+                gid.trim().parse().with_context(|| format!("Invalid tile id: {}", gid.trim()))
+            })
+            .collect(),
+        Some("base64") => {
+            let bytes = decode_base64(data.trim())?;
+            if bytes.len() % 4 != 0 {
+                anyhow::bail!("Invalid base64 layer data length: {}", bytes.len());
+            }
+            Ok(bytes.chunks(4).map(|x| u32::from_le_bytes(x.try_into().unwrap())).collect())
+        }
+        Some(encoding) => anyhow::bail!("Unknown layer encoding: {encoding}"),
+        None => anyhow::bail!("Layer data has no encoding"),
+    }
+}
+
+fn decode_base64(data: &str) -> anyhow::Result<Vec<u8>> {
+    fn value(c: u8) -> anyhow::Result<u32> {
+        Ok(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => anyhow::bail!("Invalid base64 character: {}", c as char),
+        } as u32)
+    }
+
+    let data = data.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let mut bits = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            bits |= value(c)? << (18 - 6 * i);
+        }
+        let num_bytes = chunk.len() * 6 / 8;
+        bytes.extend_from_slice(&bits.to_be_bytes()[1..1 + num_bytes]);
+    }
+    Ok(bytes)
+}
+
+// The JSON format (.tmj and .tsj)
+
+#[derive(Deserialize)]
+struct JsonMap {
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    width: usize,
+    height: usize,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    tilesets: Vec<JsonTileset>,
+    layers: Vec<JsonLayer>,
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    value: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct JsonTileset {
+    #[serde(default)]
+    firstgid: u32,
+    source: Option<String>,
+    tilewidth: Option<u32>,
+    tileheight: Option<u32>,
+    tilecount: Option<u32>,
+    #[serde(default)]
+    margin: i32,
+    #[serde(default)]
+    spacing: i32,
+    image: Option<String>,
+    #[serde(default)]
+    tiles: Vec<JsonTile>,
+}
+
+#[derive(Deserialize)]
+struct JsonTile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    animation: Vec<JsonFrame>,
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    tileid: u32,
+    duration: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonLayer {
+    TileLayer {
+        name: String,
+        data: serde_json::Value,
+        encoding: Option<String>,
+        compression: Option<String>,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+    },
+    ObjectGroup {
+        objects: Vec<JsonObject>,
+    },
+    ImageLayer {
+        #[serde(default)]
+        image: String,
+        #[serde(default)]
+        offsetx: f32,
+        #[serde(default)]
+        offsety: f32,
+        #[serde(default = "default_parallax")]
+        parallaxx: f32,
+        #[serde(default = "default_parallax")]
+        parallaxy: f32,
+        #[serde(default)]
+        repeatx: bool,
+        #[serde(default)]
+        repeaty: bool,
+    },
+    Group {
+        name: String,
+    },
+}
+
+/// Layers move along with the map unless their parallax factor is changed
+fn default_parallax() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    // Tiled 1.9 renamed `type` to `class`, and 1.10 changed it back
+    #[serde(default, rename = "type", alias = "class")]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+fn json_properties(properties: Vec<JsonProperty>) -> Properties {
+    properties
+        .into_iter()
+        .map(|property| {
+            let value = match property.value {
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            (property.name, value)
+        })
+        .collect()
+}
+
+fn parse_json_map(text: &str, dir: &Path) -> anyhow::Result<TiledMap> {
+    let map: JsonMap = serde_json::from_str(text).context("Invalid JSON")?;
+
+    let tilesets = map
+        .tilesets
+        .into_iter()
+        .map(|tileset| parse_json_tileset(tileset, dir))
+        .collect::<anyhow::Result<_>>()?;
+
+    let layers = map
+        .layers
+        .into_iter()
+        .map(|layer| {
+            Ok(match layer {
+                JsonLayer::TileLayer { name, data, encoding, compression, properties } => {
+                    let data = match data {
+                        serde_json::Value::String(data) => {
+                            decode_layer_data(&data, encoding.as_deref(), compression.as_deref())
+                                .with_context(|| format!("Invalid tile data in layer '{name}'"))?
+                        }
+                        data => serde_json::from_value(data)
+                            .with_context(|| format!("Invalid tile data in layer '{name}'"))?,
+                    };
+                    TiledLayer::Tiles { name, properties: json_properties(properties), data }
+                }
+                JsonLayer::ObjectGroup { objects } => TiledLayer::Objects {
+                    objects: objects
+                        .into_iter()
+                        .map(|object| TiledObject {
+                            class: object.class,
+                            x: object.x,
+                            y: object.y,
+                            width: object.width,
+                            height: object.height,
+                            gid: object.gid,
+                            properties: json_properties(object.properties),
+                        })
+                        .collect(),
+                },
+                JsonLayer::ImageLayer {
+                    image,
+                    offsetx,
+                    offsety,
+                    parallaxx,
+                    parallaxy,
+                    repeatx,
+                    repeaty,
+                } => TiledLayer::Image((!image.is_empty()).then(|| ParallaxLayer {
+                    texture: image,
+                    scroll: Vec2::new(parallaxx, parallaxy),
+                    offset: Vec2::new(offsetx, offsety),
+                    repeat_x: repeatx,
+                    repeat_y: repeaty,
+                })),
+                JsonLayer::Group { name } => TiledLayer::Unsupported { name, kind: "group layer" },
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(TiledMap {
+        orientation: map.orientation,
+        infinite: map.infinite,
+        width: map.width,
+        height: map.height,
+        tile_width: map.tilewidth,
+        tile_height: map.tileheight,
+        properties: json_properties(map.properties),
+        tilesets,
+        layers,
+    })
+}
+
+fn parse_json_tileset(tileset: JsonTileset, dir: &Path) -> anyhow::Result<TiledTileset> {
+    let first_gid = tileset.firstgid;
+    let (tileset, dir) = match &tileset.source {
+        Some(source) => {
+            let path = dir.join(source);
+            if path.extension().and_then(|ext| ext.to_str()) == Some("tsx") {
+                let mut tileset = load_xml_tileset(&path)?;
+                tileset.first_gid = first_gid;
+                return Ok(tileset);
+            }
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to open tileset: {}", path.display()))?;
+            let tileset: JsonTileset = serde_json::from_str(&text)
+                .with_context(|| format!("Invalid tileset: {}", path.display()))?;
+            (tileset, path.parent().unwrap_or(Path::new("")).to_path_buf())
+        }
+        None => (tileset, dir.to_path_buf()),
+    };
+
+    let missing = |field| anyhow::anyhow!("Tileset is missing '{field}'");
+    Ok(TiledTileset {
+        first_gid,
+        tile_width: tileset.tilewidth.ok_or_else(|| missing("tilewidth"))?,
+        tile_height: tileset.tileheight.ok_or_else(|| missing("tileheight"))?,
+        tile_count: tileset.tilecount.ok_or_else(|| missing("tilecount"))?,
+        margin: tileset.margin,
+        spacing: tileset.spacing,
+        image: tileset.image.map(|image| dir.join(image)),
+        animations: tileset
+            .tiles
+            .iter()
+            .map(|tile| {
+                let frames = tile.animation.iter().map(|frame| (frame.tileid, frame.duration));
+                (tile.id, frames.collect())
+            })
+            .collect(),
+        tiles: tileset
+            .tiles
+            .into_iter()
+            .map(|tile| (tile.id, json_properties(tile.properties)))
+            .collect(),
+    })
+}
+
+// The XML format (.tmx and .tsx)
+
+fn attribute<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> anyhow::Result<T> {
+    let value = node
+        .attribute(name)
+        .ok_or_else(|| anyhow::anyhow!("<{}> is missing '{name}'", node.tag_name().name()))?;
+    value.parse().map_err(|_| {
+        anyhow::anyhow!("Invalid value for '{name}' in <{}>: {value}", node.tag_name().name())
+    })
+}
+
+fn optional_attribute<T: std::str::FromStr>(
+    node: roxmltree::Node,
+    name: &str,
+    default: T,
+) -> anyhow::Result<T> {
+    match node.attribute(name) {
+        Some(_) => attribute(node, name),
+        None => Ok(default),
+    }
+}
+
+fn xml_properties(node: roxmltree::Node) -> Properties {
+    node.children()
+        .filter(|child| child.has_tag_name("properties"))
+        .flat_map(|properties| properties.children().filter(|child| child.has_tag_name("property")))
+        .filter_map(|property| {
+            let name = property.attribute("name")?.to_owned();
+            // Multi-line string properties are stored as text instead of an attribute
+            let value = property.attribute("value").or_else(|| property.text()).unwrap_or("");
+            Some((name, value.to_owned()))
+        })
+        .collect()
+}
+
+fn parse_xml_map(text: &str, dir: &Path) -> anyhow::Result<TiledMap> {
+    let document = roxmltree::Document::parse(text).context("Invalid XML")?;
+    let root = document.root_element();
+    if !root.has_tag_name("map") {
+        anyhow::bail!("Expected a <map> element, but found <{}>", root.tag_name().name());
+    }
+
+    let mut tilesets = vec![];
+    let mut layers = vec![];
+    for node in root.children().filter(|node| node.is_element()) {
+        match node.tag_name().name() {
+            "tileset" => {
+                let first_gid = attribute(node, "firstgid")?;
+                let mut tileset = match node.attribute("source") {
+                    Some(source) => load_xml_tileset(&dir.join(source))?,
+                    None => parse_xml_tileset(node, dir)?,
+                };
+                tileset.first_gid = first_gid;
+                tilesets.push(tileset);
+            }
+            "layer" => {
+                let name: String = optional_attribute(node, "name", String::new())?;
+                let data = node
+                    .children()
+                    .find(|child| child.has_tag_name("data"))
+                    .ok_or_else(|| anyhow::anyhow!("Layer '{name}' has no data"))?;
+                let data = match data.attribute("encoding") {
+                    // Without an encoding each tile is stored as its own element
+                    None => data
+                        .children()
+                        .filter(|child| child.has_tag_name("tile"))
+                        .map(|tile| optional_attribute(tile, "gid", 0))
+                        .collect::<anyhow::Result<_>>()?,
+                    encoding => decode_layer_data(
+                        data.text().unwrap_or(""),
+                        encoding,
+                        data.attribute("compression"),
+                    )
+                    .with_context(|| format!("Invalid tile data in layer '{name}'"))?,
+                };
+                layers.push(TiledLayer::Tiles { name, properties: xml_properties(node), data });
+            }
+            "objectgroup" => {
+                let objects = node
+                    .children()
+                    .filter(|child| child.has_tag_name("object"))
+                    .map(|object| {
+                        // Tiled 1.9 renamed `type` to `class`
+                        let class = object.attribute("class").or_else(|| object.attribute("type"));
+                        Ok(TiledObject {
+                            class: class.unwrap_or("").to_owned(),
+                            x: attribute(object, "x")?,
+                            y: attribute(object, "y")?,
+                            width: optional_attribute(object, "width", 0.0)?,
+                            height: optional_attribute(object, "height", 0.0)?,
+                            gid: object
+                                .attribute("gid")
+                                .map(|_| attribute(object, "gid"))
+                                .transpose()?,
+                            properties: xml_properties(object),
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                layers.push(TiledLayer::Objects { objects });
+            }
+            "imagelayer" => {
+                let image = node
+                    .children()
+                    .find(|child| child.has_tag_name("image"))
+                    .and_then(|image| image.attribute("source"));
+                let layer = match image {
+                    Some(image) => Some(ParallaxLayer {
+                        texture: image.to_owned(),
+                        scroll: Vec2::new(
+                            optional_attribute(node, "parallaxx", 1.0)?,
+                            optional_attribute(node, "parallaxy", 1.0)?,
+                        ),
+                        offset: Vec2::new(
+                            optional_attribute(node, "offsetx", 0.0)?,
+                            optional_attribute(node, "offsety", 0.0)?,
+                        ),
+                        repeat_x: optional_attribute::<u8>(node, "repeatx", 0)? != 0,
+                        repeat_y: optional_attribute::<u8>(node, "repeaty", 0)? != 0,
+                    }),
+                    None => None,
+                };
+                layers.push(TiledLayer::Image(layer));
+            }
+            "group" => layers.push(TiledLayer::Unsupported {
+                name: optional_attribute(node, "name", String::new())?,
+                kind: "group layer",
+            }),
+            _ => {}
+        }
+    }
+
+    Ok(TiledMap {
+        orientation: attribute(root, "orientation")?,
+        infinite: optional_attribute::<u8>(root, "infinite", 0)? != 0,
+        width: attribute(root, "width")?,
+        height: attribute(root, "height")?,
+        tile_width: attribute(root, "tilewidth")?,
+        tile_height: attribute(root, "tileheight")?,
+        properties: xml_properties(root),
+        tilesets,
+        layers,
+    })
+}
+
+fn load_xml_tileset(path: &Path) -> anyhow::Result<TiledTileset> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to open tileset: {}", path.display()))?;
+    let document = roxmltree::Document::parse(&text)
+        .with_context(|| format!("Invalid tileset: {}", path.display()))?;
+    parse_xml_tileset(document.root_element(), path.parent().unwrap_or(Path::new("")))
+        .with_context(|| format!("Invalid tileset: {}", path.display()))
+}
+
+fn parse_xml_tileset(node: roxmltree::Node, dir: &Path) -> anyhow::Result<TiledTileset> {
+    let image = node
+        .children()
+        .find(|child| child.has_tag_name("image"))
+        .map(|image| attribute::<String>(image, "source"))
+        .transpose()?;
+    let tiles = node
+        .children()
+        .filter(|child| child.has_tag_name("tile"))
+        .map(|tile| Ok((attribute(tile, "id")?, xml_properties(tile))))
+        .collect::<anyhow::Result<_>>()?;
+    let animations = node
+        .children()
+        .filter(|child| child.has_tag_name("tile"))
+        .map(|tile| {
+            let frames = tile
+                .children()
+                .filter(|child| child.has_tag_name("animation"))
+                .flat_map(|animation| {
+                    animation.children().filter(|child| child.has_tag_name("frame"))
+                })
+                .map(|frame| Ok((attribute(frame, "tileid")?, attribute(frame, "duration")?)))
+                .collect::<anyhow::Result<_>>()?;
+            Ok((attribute(tile, "id")?, frames))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(TiledTileset {
+        first_gid: 0,
+        tile_width: attribute(node, "tilewidth")?,
+        tile_height: attribute(node, "tileheight")?,
+        tile_count: attribute(node, "tilecount")?,
+        margin: optional_attribute(node, "margin", 0)?,
+        spacing: optional_attribute(node, "spacing", 0)?,
+        image: image.map(|image| dir.join(image)),
+        tiles,
+        animations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::Rect;
+
+    /// A 2x2 tileset of 32px tiles, where the first tile is solid
+    static TILESET_JSON: &str = r#"{
+        "firstgid": 1, "tilewidth": 32, "tileheight": 32, "tilecount": 4, "image": "tiles.png",
+        "tiles": [{ "id": 0, "properties": [{ "name": "collision", "value": "solid" }] }]
+    }"#;
+
+    fn json_map(layers: &str) -> String {
+        format!(
+            r#"{{
+                "orientation": "orthogonal", "width": 3, "height": 1,
+                "tilewidth": 32, "tileheight": 32,
+                "tilesets": [{TILESET_JSON}],
+                "layers": [{layers}]
+            }}"#
+        )
+    }
+
+    fn load_json(text: &str) -> anyhow::Result<Map> {
+        convert_map(parse_json_map(text, Path::new("maps"))?, "test".into())
+    }
+
+    #[test]
+    fn keeps_the_first_tile_apart_from_empty_space() {
+        let map = load_json(&json_map(
+            r#"{ "type": "tilelayer", "name": "collision", "data": [1, 0, 2] }"#,
+        ))
+        .unwrap();
+
+        assert_eq!(map.layers()[0].tiles, vec![1, EMPTY_TILE, 2]);
+        assert_eq!(map.tile_info_at(0, 0).collision, TileCollision::Solid);
+        assert_eq!(map.tile_info_at(1, 0).collision, TileCollision::None);
+        assert_eq!(map.tile_info_at(2, 0).collision, TileCollision::None);
+
+        // The first tile is drawn from the top left of the texture
+        let tileset = map.tileset();
+        assert_eq!(tileset.source_rect(1, 64.0), Rect::new(0.0, 0.0, 32.0, 32.0));
+        assert_eq!(tileset.source_rect(4, 64.0), Rect::new(32.0, 32.0, 32.0, 32.0));
+        assert!(tileset.check_texture_size(64, 64).is_ok());
+    }
+
+    /// Loads a map whose tileset has the given tile properties, as a list of JSON tile objects
+    fn load_with_tiles(tiles: &str) -> anyhow::Result<Map> {
+        let text = json_map(r#"{ "type": "tilelayer", "name": "collision", "data": [1, 2, 3] }"#)
+            .replace(
+                r#"[{ "id": 0, "properties": [{ "name": "collision", "value": "solid" }] }]"#,
+                tiles,
+            );
+        load_json(&text)
+    }
+
+    #[test]
+    fn supports_the_solid_property() {
+        let map = load_with_tiles(
+            r#"[
+                { "id": 0, "properties": [{ "name": "solid", "type": "bool", "value": true }] },
+                { "id": 1, "properties": [{ "name": "solid", "type": "bool", "value": false }] },
+                { "id": 2, "properties": [
+                    { "name": "solid", "type": "bool", "value": true },
+                    { "name": "collision", "value": "one_way" }
+                ] }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(map.tile_info_at(0, 0).collision, TileCollision::Solid);
+        assert_eq!(map.tile_info_at(1, 0).collision, TileCollision::None);
+        // `collision` takes priority over `solid`
+        assert_eq!(map.tile_info_at(2, 0).collision, TileCollision::OneWay);
+
+        let error = load_with_tiles(
+            r#"[{ "id": 1, "properties": [{ "name": "solid", "value": "yes" }] }]"#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error.to_string(), "Invalid 'solid' for tile 1: yes");
+    }
+
+    #[test]
+    fn rejects_invalid_animations() {
+        let cases = [
+            (
+                r#"[{ "id": 0, "animation": [
+                    { "tileid": 0, "duration": 100 }, { "tileid": 1, "duration": 200 }
+                ] }]"#,
+                "Frames of the animation of tile 0 must all have the same duration",
+            ),
+            (
+                r#"[{ "id": 1, "animation": [{ "tileid": 0, "duration": 0 }] }]"#,
+                "Frames of the animation of tile 1 must have a positive duration",
+            ),
+            (
+                r#"[{ "id": 2, "animation": [{ "tileid": 4, "duration": 100 }] }]"#,
+                "Animation of tile 2 uses a tile that is not part of the tileset",
+            ),
+        ];
+        for (tiles, expected) in cases {
+            let error = load_with_tiles(tiles).err().unwrap();
+            assert_eq!(error.to_string(), expected);
+        }
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tiled").join(name)
+    }
+
+    /// Loads a fixture map after making a change to its text
+    fn load_changed(name: &str, from: &str, to: &str) -> anyhow::Result<Map> {
+        let text = std::fs::read_to_string(fixture(name)).unwrap();
+        assert!(text.contains(from), "{name} doesn't contain {from}");
+        let text = text.replace(from, to);
+        let dir = fixture("");
+        let tiled = if name.ends_with(".tmj") {
+            parse_json_map(&text, &dir)?
+        }
+        else {
+            parse_xml_map(&text, &dir)?
+        };
+        convert_map(tiled, "test".into())
+    }
+
+    #[test]
+    fn imports_fixture_maps() {
+        for name in ["map.tmj", "map.tmx"] {
+            let map = load_map(&fixture(name)).unwrap();
+            assert_eq!(map.name, "Fixture", "{name}");
+            assert_eq!((map.width, map.height), (4, 2), "{name}");
+
+            // Layers are recognised by their name or their `kind` property, and can be stored as
+            // CSV, base64 or (in XML) separate tile elements
+            let layers: Vec<_> =
+                map.layers().iter().map(|layer| (layer.kind, layer.tiles.clone())).collect();
+            assert_eq!(
+                layers,
+                vec![
+                    (LayerKind::Background, vec![3, 3, 3, 3, 0, 0, 0, 0]),
+                    (LayerKind::Collision, vec![0, 0, 0, 0, 1, 2, 2, 1]),
+                    (LayerKind::Foreground, vec![0, 0, 4, 0, 0, 0, 0, 0]),
+                ],
+                "{name}"
+            );
+
+            let tileset = map.tileset();
+            assert_eq!(tileset.texture, fixture("tiles.png"), "{name}");
+            let collision: Vec<_> = tileset.tile_info.iter().map(|info| info.collision).collect();
+            assert_eq!(
+                collision,
+                vec![
+                    TileCollision::None,
+                    TileCollision::Solid,
+                    TileCollision::Solid,
+                    TileCollision::OneWay,
+                    TileCollision::None,
+                ],
+                "{name}"
+            );
+            let friction: Vec<_> = tileset.tile_info.iter().map(|info| info.friction).collect();
+            assert_eq!(friction, vec![1.0, 0.5, 1.0, 1.0, 1.0], "{name}");
+            // Animations use the map's tile ids and a frame time in seconds
+            let animations: Vec<_> = tileset.animations.iter().collect();
+            assert_eq!(
+                animations,
+                vec![(&4, &TileAnimation { frames: vec![4, 1], frame_time: 0.2 })],
+                "{name}"
+            );
+
+            assert_eq!(map.spawn, Some(Vec2::new(16.0, 24.0)), "{name}");
+            let objects: Vec<_> = map
+                .objects
+                .iter()
+                .map(|object| (object.kind.as_str(), object.position, object.properties.clone()))
+                .collect();
+            let properties = |pairs: &[(&str, &str)]| -> Properties {
+                pairs.iter().map(|&(key, value)| (key.into(), value.into())).collect()
+            };
+            // The note has no class, so it isn't spawned
+            assert_eq!(
+                objects,
+                vec![
+                    // Tile objects are moved up by their height, since Tiled positions them by
+                    // their bottom left corner
+                    ("cat", Vec2::new(64.0, 32.0), properties(&[("move_time", "0.25")])),
+                    (
+                        "checkpoint",
+                        Vec2::new(96.0, 0.0),
+                        properties(&[("height", "64"), ("width", "32")])
+                    ),
+                ],
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_maps() {
+        let cases = [
+            (
+                r#""orientation": "orthogonal""#,
+                r#""orientation": "isometric""#,
+                r#"orientation="orthogonal""#,
+                r#"orientation="isometric""#,
+                "Unsupported map orientation: isometric",
+            ),
+            (
+                r#""infinite": false"#,
+                r#""infinite": true"#,
+                r#"infinite="0""#,
+                r#"infinite="1""#,
+                "Infinite maps are not supported",
+            ),
+            (
+                r#""compression": """#,
+                r#""compression": "zlib""#,
+                r#"<data encoding="base64">"#,
+                r#"<data encoding="base64" compression="zlib">"#,
+                "Compressed layers (zlib) are not supported",
+            ),
+            (
+                "[0, 0, 4, 0",
+                "[0, 0, 2147483652, 0",
+                r#"<tile gid="4"/>"#,
+                r#"<tile gid="2147483652"/>"#,
+                "Flipped and rotated tiles are not supported",
+            ),
+        ];
+        for (json_from, json_to, xml_from, xml_to, expected) in cases {
+            let json = load_changed("map.tmj", json_from, json_to).err().unwrap();
+            assert!(format!("{json:#}").contains(expected), "{json:#}");
+            let xml = load_changed("map.tmx", xml_from, xml_to).err().unwrap();
+            assert!(format!("{xml:#}").contains(expected), "{xml:#}");
+        }
+    }
+}
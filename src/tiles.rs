@@ -1,36 +1,494 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
 use macroquad::{
     prelude::{Rect, WHITE},
     texture::{draw_texture_ex, DrawTextureParams, Texture2D},
 };
+use serde::Deserialize;
+
+/// How objects collide with a tile
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TileCollision {
+    /// Objects pass through the tile
+    #[default]
+    None,
+    /// Objects can not enter the tile from any side
+    Solid,
+    /// Objects can land on top of the tile, but pass through it from below and from the sides
+    OneWay,
+    /// Objects stand on the sloped surface of the tile, and pass through the empty part of it
+    Slope(Slope),
+}
+
+impl FromStr for TileCollision {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "none" => Ok(TileCollision::None),
+            "solid" => Ok(TileCollision::Solid),
+            "one_way" => Ok(TileCollision::OneWay),
+            "slope_up_45" => Ok(TileCollision::Slope(Slope::Up45)),
+            "slope_down_45" => Ok(TileCollision::Slope(Slope::Down45)),
+            "slope_up_22_low" => Ok(TileCollision::Slope(Slope::Up22Low)),
+            "slope_up_22_high" => Ok(TileCollision::Slope(Slope::Up22High)),
+            "slope_down_22_high" => Ok(TileCollision::Slope(Slope::Down22High)),
+            "slope_down_22_low" => Ok(TileCollision::Slope(Slope::Down22Low)),
+            _ => anyhow::bail!("Unknown collision kind '{s}'"),
+        }
+    }
+}
+
+impl TryFrom<String> for TileCollision {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> anyhow::Result<Self> {
+        s.parse()
+    }
+}
+
+/// The shape of a sloped tile. Slopes are named by whether they go up or down from left to right.
+/// 22.5° slopes take two tiles to rise by a full tile, so they are split into a low and a high
+/// tile.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Slope {
+    Up45,
+    Down45,
+    Up22Low,
+    Up22High,
+    Down22High,
+    Down22Low,
+}
+
+impl Slope {
+    /// Gets the height of the surface at the left and right edges of the tile, as a fraction of the
+    /// tile size
+    fn edge_heights(self) -> (f32, f32) {
+        match self {
+            Slope::Up45 => (0.0, 1.0),
+            Slope::Down45 => (1.0, 0.0),
+            Slope::Up22Low => (0.0, 0.5),
+            Slope::Up22High => (0.5, 1.0),
+            Slope::Down22High => (1.0, 0.5),
+            Slope::Down22Low => (0.5, 0.0),
+        }
+    }
+
+    /// Gets the height of the surface above the bottom of the tile
+    /// # Arguments
+    /// `x` - How far across the tile to measure the height (0.0 to 1.0)
+    /// # Return
+    /// Returns the height as a fraction of the tile size
+    pub fn height_at(self, x: f32) -> f32 {
+        let (left, right) = self.edge_heights();
+        left + (right - left) * x.clamp(0.0, 1.0)
+    }
+}
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct TileInfo {
-    pub solid: bool,
+    #[serde(default)]
+    pub collision: TileCollision,
+    #[serde(default = "default_friction")]
     pub friction: f32,
 }
 
+fn default_friction() -> f32 {
+    1.0
+}
+
+impl TileInfo {
+    /// Checks if objects can stand on top of the tile
+    pub fn is_ground(&self) -> bool {
+        self.collision != TileCollision::None
+    }
+}
+
+/// A tile that is drawn by cycling through other tiles in the tileset. Animations only change how a
+/// tile looks, it still collides using its own `TileInfo`.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TileAnimation {
+    /// The ids of the tiles to draw, in order
+    pub frames: Vec<u16>,
+    /// The number of seconds each frame is shown for
+    pub frame_time: f32,
+}
+
+impl TileAnimation {
+    /// Gets the tile to draw at a point in time
+    /// # Arguments
+    /// `time` - The seconds since the animation started
+    pub fn frame_at(&self, time: f32) -> u16 {
+        let index = (time / self.frame_time).floor() as usize % self.frames.len();
+        self.frames[index]
+    }
+}
+
+/// The physical description of the tiles used by a map. The tileset texture is kept separately and
+/// only needed for drawing.
 pub struct TileSet {
     pub tile_size: i32,
-    pub sprite: Texture2D,
+    /// The number of pixels around the edge of the texture before the first tile
+    pub margin: i32,
+    /// The number of pixels between tiles in the texture
+    pub spacing: i32,
+    /// The path to the tileset texture
+    pub texture: PathBuf,
+    /// The id of the first tile in the texture. Ids below it have no image, which lets Tiled maps
+    /// keep id 0 for empty space while still using the first tile of their texture.
+    pub first_id: u16,
     pub tile_info: Vec<TileInfo>,
+    /// The animations of the tiles that have one, by tile id
+    pub animations: HashMap<u16, TileAnimation>,
+}
+
+/// The layout of a tileset descriptor file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TileSetFile {
+    texture: PathBuf,
+    tile_size: i32,
+    #[serde(default)]
+    margin: i32,
+    #[serde(default)]
+    spacing: i32,
+    tiles: Vec<TileEntry>,
+}
+
+/// The layout of a tile in a tileset descriptor file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TileEntry {
+    #[serde(default)]
+    collision: TileCollision,
+    #[serde(default = "default_friction")]
+    friction: f32,
+    animation: Option<TileAnimation>,
 }
 
 impl TileSet {
-    pub fn id(&self, id_num: u16) -> TileInfo {
-        self.tile_info[id_num as usize]
+    /// Creates a tileset with no tiles
+    pub fn empty() -> Self {
+        Self {
+            tile_size: 32,
+            margin: 0,
+            spacing: 0,
+            texture: PathBuf::new(),
+            first_id: 0,
+            tile_info: vec![],
+            animations: HashMap::new(),
+        }
     }
 
-    pub fn draw(&self, id_num: u16, dest_rect: Rect) {
-        let source_rect = Rect::new(
-            (id_num as i32 * self.tile_size) as f32,
-            0.0,
+    /// Loads a tileset from a descriptor file. See `docs/README.md` for a description of the
+    /// format.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to open tileset: {}", path.display()))?;
+        let file: TileSetFile = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid tileset: {}", path.display()))?;
+        if file.tile_size <= 0 {
+            anyhow::bail!("Invalid tile size in {}: {}", path.display(), file.tile_size);
+        }
+        if file.margin < 0 || file.spacing < 0 {
+            anyhow::bail!("Margin and spacing in {} can not be negative", path.display());
+        }
+
+        // Tiles are checked in order, so the first broken animation is the one reported
+        let num_tiles = file.tiles.len();
+        let mut tile_info = vec![];
+        let mut animations = HashMap::new();
+        for (id, tile) in file.tiles.into_iter().enumerate() {
+            tile_info.push(TileInfo { collision: tile.collision, friction: tile.friction });
+            let Some(animation) = tile.animation
+            else {
+                continue;
+            };
+            if animation.frames.is_empty() || animation.frame_time <= 0.0 {
+                anyhow::bail!(
+                    "Animation of tile {id} in {} needs at least one frame and a positive frame \
+                     time",
+                    path.display()
+                );
+            }
+            if let Some(frame) = animation.frames.iter().find(|&&frame| frame as usize >= num_tiles)
+            {
+                anyhow::bail!(
+                    "Animation of tile {id} in {} uses tile {frame}, but the tileset only has \
+                     {num_tiles} tiles",
+                    path.display()
+                );
+            }
+            animations.insert(id as u16, animation);
+        }
+
+        // The texture path is relative to the descriptor
+        let texture = path.parent().unwrap_or(Path::new("")).join(file.texture);
+        Ok(Self {
+            tile_size: file.tile_size,
+            margin: file.margin,
+            spacing: file.spacing,
+            texture,
+            first_id: 0,
+            tile_info,
+            animations,
+        })
+    }
+
+    /// Calculates how many tiles fit along one side of the texture
+    /// # Arguments
+    /// `length` - The width or height of the texture in pixels
+    /// # Return
+    /// Returns the number of tiles, and whether the tiles exactly fill the texture
+    fn tiles_along(&self, length: i32) -> (i32, bool) {
+        // Each tile is followed by spacing, apart from the last one
+        let available = length - 2 * self.margin + self.spacing;
+        let stride = self.tile_size + self.spacing;
+        (available.max(0) / stride, available >= 0 && available % stride == 0)
+    }
+
+    /// Checks that the tileset texture has the same number of tiles as the tileset
+    /// # Arguments
+    /// `width` - The width of the texture in pixels
+    /// `height` - The height of the texture in pixels
+    pub fn check_texture_size(&self, width: u32, height: u32) -> anyhow::Result<()> {
+        let (columns, fits_x) = self.tiles_along(width as i32);
+        let (rows, fits_y) = self.tiles_along(height as i32);
+        if !fits_x || !fits_y {
+            anyhow::bail!(
+                "{} is {width}x{height}, which doesn't fit a whole number of {}px tiles with a \
+                 margin of {} and spacing of {}",
+                self.texture.display(),
+                self.tile_size,
+                self.margin,
+                self.spacing
+            );
+        }
+        let num_tiles = columns * rows;
+        let described = self.tile_info.len().saturating_sub(self.first_id as usize);
+        if num_tiles as usize != described {
+            anyhow::bail!(
+                "{} has {num_tiles} tiles, but the tileset describes {described}",
+                self.texture.display(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Gets the area of the texture containing a tile. Tiles are numbered left to right, then top
+    /// to bottom, starting from `first_id`.
+    /// # Arguments
+    /// `id_num` - The id of the tile
+    /// `texture_width` - The width of the tileset texture in pixels
+    pub fn source_rect(&self, id_num: u16, texture_width: f32) -> Rect {
+        let columns = self.tiles_along(texture_width as i32).0.max(1);
+        let index = id_num.saturating_sub(self.first_id) as i32;
+        let column = index % columns;
+        let row = index / columns;
+        let stride = self.tile_size + self.spacing;
+        Rect::new(
+            (self.margin + column * stride) as f32,
+            (self.margin + row * stride) as f32,
             self.tile_size as f32,
             self.tile_size as f32,
-        );
-        draw_texture_ex(self.sprite, dest_rect.x, dest_rect.y, WHITE, DrawTextureParams {
+        )
+    }
+
+    pub fn id(&self, id_num: u16) -> TileInfo {
+        self.tile_info[id_num as usize]
+    }
+
+    /// Gets the tile to draw for a tile id, following its animation if it has one
+    /// # Arguments
+    /// `id_num` - The id of the tile
+    /// `time` - The seconds since the map started
+    pub fn frame_at(&self, id_num: u16, time: f32) -> u16 {
+        match self.animations.get(&id_num) {
+            Some(animation) => animation.frame_at(time),
+            None => id_num,
+        }
+    }
+
+    pub fn draw(&self, texture: Texture2D, id_num: u16, dest_rect: Rect) {
+        let source_rect = self.source_rect(id_num, texture.width());
+        draw_texture_ex(texture, dest_rect.x, dest_rect.y, WHITE, DrawTextureParams {
             dest_size: Some(dest_rect.size()),
             source: Some(source_rect),
             ..Default::default()
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn animated_tiles_cycle_through_frames() {
+        let mut tileset = TileSet::empty();
+        tileset.animations.insert(2, TileAnimation { frames: vec![2, 5, 7], frame_time: 0.25 });
+
+        assert_eq!(tileset.frame_at(2, 0.0), 2);
+        assert_eq!(tileset.frame_at(2, 0.3), 5);
+        assert_eq!(tileset.frame_at(2, 0.6), 7);
+        // The animation loops once every frame has been shown
+        assert_eq!(tileset.frame_at(2, 0.8), 2);
+        // Tiles without an animation are always drawn as themselves
+        assert_eq!(tileset.frame_at(3, 0.3), 3);
+    }
+
+    /// Writes a tileset descriptor to a temporary directory and loads it
+    fn load_descriptor(name: &str, json: &str) -> anyhow::Result<TileSet> {
+        let dir = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("tiles.json");
+        std::fs::write(&path, json)?;
+        let tileset = TileSet::load(&path);
+        std::fs::remove_dir_all(&dir)?;
+        tileset.map(|tileset| {
+            // Only check the part of the texture path that doesn't depend on the temp directory
+            let texture = tileset.texture.strip_prefix(&dir).unwrap().to_path_buf();
+            TileSet { texture, ..tileset }
+        })
+    }
+
+    #[test]
+    fn loads_tileset_descriptors() {
+        let tileset = load_descriptor(
+            "tileset-descriptor",
+            r#"{
+                "texture": "images/tiles.png",
+                "tile_size": 16,
+                "tiles": [
+                    {},
+                    { "collision": "solid", "friction": 0.5 },
+                    { "collision": "slope_up_45" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(tileset.tile_size, 16);
+        assert_eq!((tileset.margin, tileset.spacing), (0, 0));
+        assert_eq!(tileset.first_id, 0);
+        // The texture is found next to the descriptor
+        assert_eq!(tileset.texture, Path::new("images/tiles.png"));
+        assert_eq!(tileset.tile_info.len(), 3);
+        assert_eq!(tileset.id(0).collision, TileCollision::None);
+        assert_eq!(tileset.id(0).friction, 1.0);
+        assert_eq!(tileset.id(1).collision, TileCollision::Solid);
+        assert_eq!(tileset.id(1).friction, 0.5);
+        assert_eq!(tileset.id(2).collision, TileCollision::Slope(Slope::Up45));
+        assert_eq!(tileset.id(2).friction, 1.0);
+        assert!(tileset.animations.is_empty());
+    }
+
+    #[test]
+    fn loads_tile_animations() {
+        let tileset = load_descriptor(
+            "tileset-animations",
+            r#"{
+                "texture": "tiles.png",
+                "tile_size": 16,
+                "tiles": [
+                    { "collision": "solid", "animation": { "frames": [0, 2], "frame_time": 0.5 } },
+                    {},
+                    {}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(tileset.animations.len(), 1);
+        assert_eq!(tileset.animations[&0], TileAnimation { frames: vec![0, 2], frame_time: 0.5 });
+        // Animated tiles keep their own collision
+        assert_eq!(tileset.id(0).collision, TileCollision::Solid);
+
+        let cases = [
+            (r#"{ "frames": [], "frame_time": 0.5 }"#, "needs at least one frame"),
+            (r#"{ "frames": [1], "frame_time": 0.0 }"#, "needs at least one frame"),
+            (r#"{ "frames": [1], "frame_time": -1.0 }"#, "positive frame time"),
+            (
+                r#"{ "frames": [0, 3], "frame_time": 0.5 }"#,
+                "uses tile 3, but the tileset only has 2",
+            ),
+        ];
+        for (animation, expected) in cases {
+            let json = format!(
+                r#"{{
+                    "texture": "tiles.png",
+                    "tile_size": 16,
+                    "tiles": [{{ "animation": {animation} }}, {{}}]
+                }}"#
+            );
+            let err = load_descriptor("tileset-invalid-animation", &json).err().unwrap();
+            assert!(err.to_string().contains(expected), "{animation}: {err}");
+        }
+
+        // With several broken animations, the one on the first tile is always reported
+        let json = format!(
+            r#"{{
+                "texture": "tiles.png",
+                "tile_size": 16,
+                "tiles": [{}]
+            }}"#,
+            vec![r#"{ "animation": { "frames": [], "frame_time": 0.5 } }"#; 16].join(", ")
+        );
+        let err = load_descriptor("tileset-invalid-animations", &json).err().unwrap();
+        assert!(err.to_string().starts_with("Animation of tile 0 "), "{err}");
+    }
+
+    #[test]
+    fn checks_the_texture_size() {
+        let mut tileset = TileSet::empty();
+        tileset.tile_size = 16;
+        tileset.tile_info = vec![TileInfo { collision: TileCollision::None, friction: 1.0 }; 6];
+
+        assert!(tileset.check_texture_size(48, 32).is_ok());
+        assert!(tileset.check_texture_size(32, 48).is_ok());
+        // A texture with a different number of tiles
+        let err = tileset.check_texture_size(64, 32).unwrap_err();
+        assert!(err.to_string().contains("has 8 tiles, but the tileset describes 6"), "{err}");
+        // A texture that doesn't fit a whole number of tiles
+        assert!(tileset.check_texture_size(50, 32).is_err());
+
+        tileset.margin = 2;
+        tileset.spacing = 1;
+        assert!(tileset.check_texture_size(54, 37).is_ok());
+        assert!(tileset.check_texture_size(48, 32).is_err());
+    }
+
+    #[test]
+    fn finds_tiles_in_textures_with_margin_and_spacing() {
+        let mut tileset = TileSet::empty();
+        tileset.tile_size = 16;
+        tileset.margin = 2;
+        tileset.spacing = 1;
+
+        // A 54px wide texture fits 3 columns of tiles
+        let cases = [
+            (0, Rect::new(2.0, 2.0, 16.0, 16.0)),
+            (1, Rect::new(19.0, 2.0, 16.0, 16.0)),
+            (2, Rect::new(36.0, 2.0, 16.0, 16.0)),
+            // The first tile of the second row
+            (3, Rect::new(2.0, 19.0, 16.0, 16.0)),
+            (4, Rect::new(19.0, 19.0, 16.0, 16.0)),
+            (8, Rect::new(36.0, 36.0, 16.0, 16.0)),
+        ];
+        for (id, expected) in cases {
+            assert_eq!(tileset.source_rect(id, 54.0), expected, "tile {id}");
+        }
+
+        // Tilesets from Tiled start numbering the texture from 1
+        tileset.first_id = 1;
+        assert_eq!(tileset.source_rect(1, 54.0), Rect::new(2.0, 2.0, 16.0, 16.0));
+        assert_eq!(tileset.source_rect(4, 54.0), Rect::new(2.0, 19.0, 16.0, 16.0));
+    }
+}
@@ -0,0 +1,118 @@
+/// The longest frame that will be simulated. Any time beyond this is dropped so that a long stall
+/// (e.g. dragging the window) doesn't cause the simulation to spend several frames catching up.
+static MAX_FRAME_TIME: f32 = 0.25;
+
+/// An accumulator for stepping the simulation at a fixed rate, independent of the render frame
+/// rate. Every tick is the same length, so identical inputs always produce identical results.
+pub struct FixedTimestep {
+    tick: f32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Create a new fixed timestep
+    /// # Arguments
+    /// `ticks_per_second` - The number of simulation steps to run per second
+    pub fn new(ticks_per_second: u32) -> Self {
+        assert!(ticks_per_second > 0);
+        Self { tick: 1.0 / ticks_per_second as f32, accumulator: 0.0 }
+    }
+
+    /// Gets the length of a single tick
+    /// # Return
+    /// Returns the number of seconds simulated by each tick
+    pub fn dt(&self) -> f32 {
+        self.tick
+    }
+
+    /// Adds the elapsed frame time to the accumulator
+    /// # Arguments
+    /// `frame_secs` - The time elapsed since the last frame
+    /// # Return
+    /// Returns the number of whole ticks that should be simulated this frame
+    pub fn advance(&mut self, frame_secs: f32) -> u32 {
+        self.accumulator += frame_secs.clamp(0.0, MAX_FRAME_TIME);
+        let ticks = (self.accumulator / self.tick).floor();
+        self.accumulator -= ticks * self.tick;
+        ticks as u32
+    }
+
+    /// Gets how far the simulation is between the last tick and the next one
+    /// # Return
+    /// Returns a value between 0.0 and 1.0 to interpolate the last two ticks by
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.tick).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::Vec2;
+
+    use super::*;
+    use crate::{
+        controller::Controller,
+        entity::{creature::Creature, Entity, Physics},
+        fixtures,
+    };
+
+    static TICKS_PER_SECOND: u32 = 60;
+    static NUM_TICKS: usize = 300;
+
+    /// Runs right then left, jumping twice along the way
+    struct ScriptedController {
+        tick: usize,
+    }
+
+    impl Controller<Creature> for ScriptedController {
+        fn update(&mut self, object: &mut Creature, _: f32) {
+            let dir = if self.tick < 150 { 1.0 } else { -1.0 };
+            object.accel.x = dir * object.move_accel;
+            let jump = (30..50).contains(&self.tick) || (200..260).contains(&self.tick);
+            if jump && object.is_on_ground() {
+                object.vel.y -= object.jump_accel;
+            }
+            self.tick += 1;
+        }
+    }
+
+    /// Runs a creature through the same input with a different frame time each frame
+    /// # Return
+    /// Returns the position of the creature after every tick
+    fn simulate(frame_times: impl Iterator<Item = f32>) -> Vec<Vec2> {
+        // A room with a solid floor and walls
+        let map = fixtures::map(20, 10, |x, y| x == 0 || x == 19 || y == 9);
+        let creature = fixtures::creature(Vec2::new(64.0, 64.0), Vec2::new(32.0, 64.0));
+        let mut entity = Entity::new(creature, ScriptedController { tick: 0 });
+
+        let mut timestep = FixedTimestep::new(TICKS_PER_SECOND);
+        let mut positions = vec![];
+        for frame_secs in frame_times {
+            for _ in 0..timestep.advance(frame_secs) {
+                if positions.len() == NUM_TICKS {
+                    return positions;
+                }
+                entity.update(&map, timestep.dt());
+                positions.push(entity.object.pos);
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn positions_do_not_depend_on_the_frame_rate() {
+        let steady = simulate(std::iter::repeat(1.0 / 30.0));
+        // Mostly 144 fps, with the occasional slow frame and a long stall
+        let jittery = [1.0 / 144.0, 1.0 / 144.0, 1.0 / 60.0, 1.0 / 144.0, 0.05, 1.0 / 240.0, 0.3];
+        let jittery = simulate(jittery.into_iter().cycle());
+
+        assert_eq!(steady.len(), NUM_TICKS);
+        assert_eq!(jittery.len(), NUM_TICKS);
+        // The creature has to actually move for the comparison to mean anything
+        assert!(steady.iter().any(|&position| position != steady[0]));
+        let bits = |positions: &[Vec2]| -> Vec<[u32; 2]> {
+            positions.iter().map(|position| position.to_array().map(f32::to_bits)).collect()
+        };
+        assert_eq!(bits(&steady), bits(&jittery));
+    }
+}
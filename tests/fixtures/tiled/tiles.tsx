@@ -0,0 +1,26 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" name="tiles" tilewidth="32" tileheight="32" tilecount="4" columns="2">
+ <image source="tiles.png" width="64" height="64"/>
+ <tile id="0">
+  <properties>
+   <property name="collision" value="solid"/>
+   <property name="friction" type="float" value="0.5"/>
+  </properties>
+ </tile>
+ <tile id="1">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="2">
+  <properties>
+   <property name="collision" value="one_way"/>
+  </properties>
+ </tile>
+ <tile id="3">
+  <animation>
+   <frame tileid="3" duration="200"/>
+   <frame tileid="0" duration="200"/>
+  </animation>
+ </tile>
+</tileset>